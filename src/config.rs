@@ -1,16 +1,45 @@
 use std::collections::HashMap;
 
+#[cfg(feature = "clap")]
+use crate::config_layer::ClapConfigLayer;
 use crate::config_layer::{
-    handlers, ConfigLayer, EnvironmentConfigLayer, FileConfigLayer, MemoryConfigLayer,
+    handlers, ArgsConfigLayer, ConfigLayer, ConfigLayerError, DotenvConfigLayer,
+    EnvironmentConfigLayer, FileConfigLayer, MemoryConfigLayer,
 };
+use crate::config_origins::AnnotatedValue;
 use crate::file_finder::FileExtension;
-use crate::{Result, Value};
+use crate::model::{IntoValuePath, MergeOptions, MergeStrategy, ModelError, Schema};
+use crate::{BakuninError, Result, Value};
+
+/// Defines what happens when a built value doesn't match the configuration schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaPolicy {
+    /// Building the value fails with `ModelError::SchemaValidation`
+    Refuse,
+    /// Errors are logged as warnings and the value is returned
+    Warn,
+}
+
+/// Defines what `BakuninConfig::save_layer` writes to a layer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SaveMode {
+    /// The whole value is written
+    #[default]
+    Full,
+    /// Only the keys that differ from the merge of the layers below are written, so
+    /// values inherited from lower layers, like defaults, are not copied to the layer
+    Delta,
+}
 
 /// A configuration builder that allows adding multiple configuration layers
 /// and building a final configuration value by merging the values from all layers.
 pub struct BakuninConfig {
     layers_names: Vec<&'static str>,
     layers: HashMap<&'static str, Box<dyn ConfigLayer>>,
+    merge_options: MergeOptions,
+    schema: Option<(Schema, SchemaPolicy)>,
+    save_mode: SaveMode,
+    write_target: Option<&'static str>,
 }
 
 impl BakuninConfig {
@@ -18,9 +47,46 @@ impl BakuninConfig {
         BakuninConfig {
             layers_names: Vec::new(),
             layers: HashMap::new(),
+            merge_options: MergeOptions::default(),
+            schema: None,
+            save_mode: SaveMode::default(),
+            write_target: None,
         }
     }
 
+    /// Sets the options used to merge the values of each layer.
+    pub fn set_merge_options(&mut self, options: MergeOptions) {
+        self.merge_options = options;
+    }
+
+    /// Like `set_merge_options`, but returns `self` for method chaining.
+    pub fn with_merge_options(mut self, options: MergeOptions) -> Self {
+        self.set_merge_options(options);
+        self
+    }
+
+    /// Sets the default strategy used to merge arrays from different layers.
+    pub fn with_merge_strategy(mut self, strategy: MergeStrategy) -> Self {
+        self.merge_options.set_strategy(strategy);
+        self
+    }
+
+    /// Sets the strategy used to merge the array at `path`, overriding the default one.
+    ///
+    /// Returns an error if the path is not valid.
+    pub fn with_path_merge_strategy<P: IntoValuePath>(
+        mut self,
+        path: P,
+        strategy: MergeStrategy,
+    ) -> Result<Self> {
+        self.merge_options.set_path_strategy(path, strategy)?;
+        Ok(self)
+    }
+
+    pub fn get_merge_options(&self) -> &MergeOptions {
+        &self.merge_options
+    }
+
     /// Add a new configuration layer to the builder.
     ///
     /// If a layer with the same name already exists, it will be ignored.
@@ -74,6 +140,28 @@ impl BakuninConfig {
             }
         }
 
+        #[cfg(feature = "json5")]
+        {
+            if FileExtension::Json5.match_path(&path) || FileExtension::Jsonc.match_path(&path) {
+                self.push_layer(
+                    name,
+                    Box::new(FileConfigLayer::<handlers::Json5FileHandler>::new(path)),
+                );
+                return Ok(());
+            }
+        }
+
+        #[cfg(feature = "yaml")]
+        {
+            if FileExtension::Yaml.match_path(&path) {
+                self.push_layer(
+                    name,
+                    Box::new(FileConfigLayer::<handlers::YamlFileHandler>::new(path)),
+                );
+                return Ok(());
+            }
+        }
+
         let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
         return Err(crate::config_layer::ConfigLayerError::UnsupportedExtension(ext.into()).into());
     }
@@ -103,6 +191,132 @@ impl BakuninConfig {
         self
     }
 
+    /// Adds an environment variable layer that reads variables with any of the `prefixes`,
+    /// and variables mapped to key paths by `mappings`, like `("DATABASE_URL", "database.url")`.
+    ///
+    /// Returns an error if any of the paths is not valid.
+    pub fn add_environment_mappings(
+        &mut self,
+        name: &'static str,
+        prefixes: &[&'static str],
+        mappings: &[(&str, &str)],
+    ) -> Result<()> {
+        let mut layer = EnvironmentConfigLayer::unprefixed();
+        for prefix in prefixes {
+            layer.add_prefix(prefix);
+        }
+        for (var, path) in mappings {
+            layer.add_mapping(var, *path, None)?;
+        }
+
+        self.push_layer(name, Box::new(layer));
+        Ok(())
+    }
+
+    /// Adds an environment variable layer with prefixes and mappings.
+    ///
+    /// This method is a convenience wrapper around `add_environment_mappings` that returns `Result<Self>`.
+    pub fn with_environment_mappings(
+        mut self,
+        name: &'static str,
+        prefixes: &[&'static str],
+        mappings: &[(&str, &str)],
+    ) -> Result<Self> {
+        self.add_environment_mappings(name, prefixes, mappings)?;
+        Ok(self)
+    }
+
+    /// Adds a layer with the command-line arguments of the current process,
+    /// like `--log.level=debug` or `-o key=value`.
+    pub fn add_args_layer(&mut self, name: &'static str) {
+        self.push_layer(name, Box::new(ArgsConfigLayer::new()));
+    }
+
+    /// Adds a command-line arguments layer to the configuration builder.
+    ///
+    /// This method is a convenience wrapper around `add_args_layer` that returns `Self`.
+    pub fn with_args_layer(mut self, name: &'static str) -> Self {
+        self.add_args_layer(name);
+        self
+    }
+
+    /// Adds a layer with the values of parsed `clap` arguments supplied by the user.
+    #[cfg(feature = "clap")]
+    pub fn add_clap_layer(&mut self, name: &'static str, matches: clap::ArgMatches) {
+        self.push_layer(name, Box::new(ClapConfigLayer::new(matches)));
+    }
+
+    /// Adds a `clap` arguments layer to the configuration builder.
+    ///
+    /// This method is a convenience wrapper around `add_clap_layer` that returns `Self`.
+    #[cfg(feature = "clap")]
+    pub fn with_clap_layer(mut self, name: &'static str, matches: clap::ArgMatches) -> Self {
+        self.add_clap_layer(name, matches);
+        self
+    }
+
+    /// Adds a file layer for the path of the `--config <PATH>` argument, added with
+    /// `ClapConfigLayer::with_config_arg`. Does nothing if the argument wasn't supplied.
+    ///
+    /// Fails with `ConfigLayerError::InvalidArgument` if the command has no `config`
+    /// argument or its values are not `PathBuf`.
+    #[cfg(feature = "clap")]
+    pub fn add_config_arg_layer(
+        &mut self,
+        name: &'static str,
+        matches: &clap::ArgMatches,
+    ) -> Result<()> {
+        let id = crate::config_layer::CONFIG_ARG_ID;
+        let path = matches.try_get_one::<std::path::PathBuf>(id).map_err(|e| {
+            ConfigLayerError::InvalidArgument {
+                arg: id.to_string(),
+                why: e.to_string(),
+            }
+        })?;
+
+        match path {
+            Some(path) => self.add_file_layer(name, path.clone()),
+            None => Ok(()),
+        }
+    }
+
+    /// Adds a file layer for the `--config <PATH>` argument to the configuration builder.
+    ///
+    /// This method is a convenience wrapper around `add_config_arg_layer` that returns `Self`.
+    #[cfg(feature = "clap")]
+    pub fn with_config_arg_layer(
+        mut self,
+        name: &'static str,
+        matches: &clap::ArgMatches,
+    ) -> Result<Self> {
+        self.add_config_arg_layer(name, matches)?;
+        Ok(self)
+    }
+
+    /// Adds a dotenv (`.env`) file layer, reading variables with the rules of `env`,
+    /// like its prefixes and separator.
+    pub fn add_dotenv_layer(
+        &mut self,
+        name: &'static str,
+        path: std::path::PathBuf,
+        env: EnvironmentConfigLayer,
+    ) {
+        self.push_layer(name, Box::new(DotenvConfigLayer::new(path, env)));
+    }
+
+    /// Adds a dotenv (`.env`) file layer to the configuration builder.
+    ///
+    /// This method is a convenience wrapper around `add_dotenv_layer` that returns `Self`.
+    pub fn with_dotenv_layer(
+        mut self,
+        name: &'static str,
+        path: std::path::PathBuf,
+        env: EnvironmentConfigLayer,
+    ) -> Self {
+        self.add_dotenv_layer(name, path, env);
+        self
+    }
+
     /// Adds a memory layer to the configuration builder.
     ///
     /// This layer is useful for testing or when you want to provide a default configuration
@@ -118,33 +332,267 @@ impl BakuninConfig {
         self
     }
 
+    /// Sets a schema to validate the value built from all layers.
+    pub fn set_schema(&mut self, schema: Schema, policy: SchemaPolicy) {
+        self.schema = Some((schema, policy));
+    }
+
+    /// Like `set_schema`, but returns `self` for method chaining.
+    pub fn with_schema(mut self, schema: Schema, policy: SchemaPolicy) -> Self {
+        self.set_schema(schema, policy);
+        self
+    }
+
     pub fn get_layer(&self, name: &'static str) -> Option<&Box<dyn ConfigLayer>> {
         self.layers.get(name)
     }
 
+    /// Reads the value of the layer `name`, applies `update` and writes it back.
+    ///
+    /// File layers hold a lock during the whole update, so concurrent writers don't
+    /// overwrite each other's changes. Fails with `ConfigLayerError::WriteNotSupported` if
+    /// the layer can't be written.
+    pub fn update_layer<F>(&self, name: &str, mut update: F) -> Result<()>
+    where
+        F: FnMut(&mut Value) -> Result<()>,
+    {
+        let layer = self.find_writable_layer(name)?;
+        read_only_error(name, layer.update_value(&mut update))
+    }
+
+    pub fn get_save_mode(&self) -> SaveMode {
+        self.save_mode
+    }
+
+    /// Sets what `save_layer` writes, `SaveMode::Full` by default.
+    pub fn set_save_mode(&mut self, save_mode: SaveMode) {
+        self.save_mode = save_mode;
+    }
+
+    /// Like `set_save_mode`, but returns `self` for method chaining.
+    pub fn with_save_mode(mut self, save_mode: SaveMode) -> Self {
+        self.set_save_mode(save_mode);
+        self
+    }
+
+    /// Saves `value` to the layer `name`, so it becomes the effective value of the layers
+    /// up to `name`.
+    ///
+    /// With `SaveMode::Delta`, only the keys that differ from the merge of the layers added
+    /// before `name` are written, and keys missing from `value` are written with the unset
+    /// sentinel, so removing an inherited key fails if there's none. Keys of the layer that
+    /// now equal the inherited value are removed.
+    pub fn save_layer(&self, name: &str, value: &Value) -> Result<()> {
+        let layer = self.find_writable_layer(name)?;
+
+        match self.save_mode {
+            SaveMode::Full => read_only_error(name, layer.write_value(value)),
+            SaveMode::Delta => {
+                let delta = value.diff_with(&self.build_below(name)?, &self.merge_options)?;
+                self.update_layer(name, |layer_value| {
+                    *layer_value = delta.clone();
+                    Ok(())
+                })
+            }
+        }
+    }
+
+    /// Returns the names of the layers that can be written, in the order they were added.
+    pub fn get_writable_layers(&self) -> Vec<&'static str> {
+        self.layers_names
+            .iter()
+            .filter(|name| {
+                self.layers
+                    .get(*name)
+                    .is_some_and(|layer| layer.can_write())
+            })
+            .copied()
+            .collect()
+    }
+
+    /// Returns the layer written by `save`: the one set with `set_write_target` or,
+    /// by default, the last writable layer added, usually the user's configuration file.
+    pub fn get_write_target(&self) -> Option<&'static str> {
+        self.write_target
+            .or_else(|| self.get_writable_layers().last().copied())
+    }
+
+    /// Sets the layer written by `save`, instead of the last writable layer.
+    pub fn set_write_target(&mut self, name: &'static str) {
+        self.write_target = Some(name);
+    }
+
+    /// Like `set_write_target`, but returns `self` for method chaining.
+    pub fn with_write_target(mut self, name: &'static str) -> Self {
+        self.set_write_target(name);
+        self
+    }
+
+    /// Saves `value` to the write target with `save_layer`.
+    ///
+    /// Fails with `ConfigLayerError::WriteNotSupported` if no layer can be written.
+    pub fn save(&self, value: &Value) -> Result<()> {
+        match self.get_write_target() {
+            Some(name) => self.save_layer(name, value),
+            None => Err(ConfigLayerError::WriteNotSupported(None).into()),
+        }
+    }
+
+    fn find_layer(&self, name: &str) -> Result<&dyn ConfigLayer> {
+        self.layers
+            .get(name)
+            .map(|layer| layer.as_ref())
+            .ok_or_else(|| ConfigLayerError::LayerNotFound(name.to_string()).into())
+    }
+
+    fn find_writable_layer(&self, name: &str) -> Result<&dyn ConfigLayer> {
+        let layer = self.find_layer(name)?;
+        if !layer.can_write() {
+            return Err(ConfigLayerError::WriteNotSupported(Some(name.to_string())).into());
+        }
+
+        Ok(layer)
+    }
+
+    /// Merges the values of the layers added before the layer `name`.
+    fn build_below(&self, name: &str) -> Result<Value> {
+        let position = self.layers_names.iter().position(|n| *n == name);
+        let below = &self.layers_names[..position.unwrap_or(self.layers_names.len())];
+        let mut value = Value::new_map();
+
+        for (_, _, layer_value) in self.read_layers_in(below, false)? {
+            value.merge_with(&layer_value, &self.merge_options);
+        }
+
+        Ok(value)
+    }
+
+    /// Sets the value at `path` in the layer `name`, keeping its other values.
+    pub fn set_in_layer<P: IntoValuePath>(&self, name: &str, path: P, value: Value) -> Result<()> {
+        let path = path.into_value_path()?;
+        self.update_layer(name, |layer_value| {
+            layer_value.set_path(&path, value.clone())?;
+            Ok(())
+        })
+    }
+
+    /// Removes the value at `path` from the layer `name`, returning it if it existed.
+    pub fn remove_in_layer<P: IntoValuePath>(&self, name: &str, path: P) -> Result<Option<Value>> {
+        let path = path.into_value_path()?;
+        let mut removed = None;
+        self.update_layer(name, |layer_value| {
+            removed = layer_value.remove_path(&path)?;
+            Ok(())
+        })?;
+        Ok(removed)
+    }
+
     /// Builds the configuration value by reading from all layers in the order they were added.
     /// If `skip_on_error` is true, it will skip layers that return an error
     pub fn build_value(&self, skip_on_error: bool) -> Result<Value> {
         let mut value = Value::new_map();
 
-        for name in &self.layers_names {
+        for (_, _, layer_value) in self.read_layers(skip_on_error)? {
+            // Merge the layer value into the main value
+            value.merge_with(&layer_value, &self.merge_options);
+        }
+
+        self.check_schema(&value)?;
+        Ok(value)
+    }
+
+    /// Builds the configuration value like `build_value`, but also records which
+    /// layer supplied each value and which values it shadowed.
+    pub fn build_with_origins(&self, skip_on_error: bool) -> Result<AnnotatedValue> {
+        let annotated = self.merge_with_origins(skip_on_error)?;
+        self.check_schema(annotated.get_value())?;
+        Ok(annotated)
+    }
+
+    /// Builds the configuration and describes which layers set the value at `path`,
+    /// from the effective one to the lowest shadowed one.
+    ///
+    /// Layers that fail to read are skipped. The value is not checked against the schema,
+    /// so invalid values can be explained too.
+    pub fn explain<P: IntoValuePath>(&self, path: P) -> Result<String> {
+        let path = path.into_value_path()?;
+        Ok(self.merge_with_origins(true)?.explain(&path))
+    }
+
+    /// Merges the layers recording the origin of each value, without checking the schema.
+    fn merge_with_origins(&self, skip_on_error: bool) -> Result<AnnotatedValue> {
+        let mut annotated = AnnotatedValue::new();
+
+        for (name, layer, layer_value) in self.read_layers(skip_on_error)? {
+            annotated.merge_layer(name, layer, &layer_value, &self.merge_options);
+        }
+
+        Ok(annotated)
+    }
+
+    /// Validates the built value with the schema, if defined.
+    fn check_schema(&self, value: &Value) -> Result<()> {
+        let (schema, policy) = match &self.schema {
+            Some(schema) => schema,
+            None => return Ok(()),
+        };
+
+        let errors = schema.check(value);
+        if errors.is_empty() {
+            return Ok(());
+        }
+
+        match policy {
+            SchemaPolicy::Refuse => Err(ModelError::SchemaValidation(errors).into()),
+            SchemaPolicy::Warn => {
+                for error in errors {
+                    log::warn!("Invalid configuration value: {}", error);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Reads the value of every layer in the order they were added.
+    fn read_layers(
+        &self,
+        skip_on_error: bool,
+    ) -> Result<Vec<(&'static str, &dyn ConfigLayer, Value)>> {
+        self.read_layers_in(&self.layers_names, skip_on_error)
+    }
+
+    /// Reads the value of the layers `names`, in the given order.
+    fn read_layers_in(
+        &self,
+        names: &[&'static str],
+        skip_on_error: bool,
+    ) -> Result<Vec<(&'static str, &dyn ConfigLayer, Value)>> {
+        let mut values = Vec::new();
+
+        for name in names {
             if let Some(layer) = self.layers.get(name) {
-                let layer_value = layer.read_value();
-                if let Err(e) = layer_value {
-                    if skip_on_error {
+                match layer.read_value() {
+                    Ok(layer_value) => values.push((*name, layer.as_ref(), layer_value)),
+                    Err(e) if skip_on_error => {
                         log::warn!("Error reading config layer '{}': {}", name, e);
                         continue; // Skip this layer on error
-                    } else {
-                        return Err(e);
                     }
-                } else if let Ok(layer_value) = layer_value {
-                    // Merge the layer value into the main value
-                    value.merge(&layer_value);
+                    Err(e) => return Err(e),
                 }
             }
         }
 
-        Ok(value)
+        Ok(values)
+    }
+}
+
+/// Sets the name of the layer in `WriteNotSupported` errors.
+fn read_only_error(name: &str, result: Result<()>) -> Result<()> {
+    match result {
+        Err(BakuninError::ConfigLayerError(ConfigLayerError::WriteNotSupported(None))) => {
+            Err(ConfigLayerError::WriteNotSupported(Some(name.to_string())).into())
+        }
+        result => result,
     }
 }
 
@@ -161,3 +609,239 @@ impl std::fmt::Debug for BakuninConfig {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{value_map, value_vec};
+
+    #[test]
+    fn build_value_with_merge_strategy() {
+        let config = BakuninConfig::new()
+            .with_memory_layer(
+                "default",
+                value_map! { plugins: value_vec!["a"], hosts: value_vec!["x"] },
+            )
+            .with_memory_layer(
+                "local",
+                value_map! { plugins: value_vec!["b"], hosts: value_vec!["y"] },
+            )
+            .with_merge_strategy(MergeStrategy::Append)
+            .with_path_merge_strategy("hosts", MergeStrategy::Replace)
+            .unwrap();
+
+        let v = config.build_value(false).unwrap();
+
+        assert_eq!(v.get("plugins"), value_vec!["a", "b"]);
+        assert_eq!(v.get("hosts"), value_vec!["y"]);
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn add_file_layer_yaml() {
+        let path = std::path::PathBuf::from("./target/test_config_layer.yml");
+        std::fs::write(&path, "server:\n  port: 8080\n").unwrap();
+
+        let config = BakuninConfig::new()
+            .with_memory_layer("default", value_map! { name: "app" })
+            .with_file_layer("local", path)
+            .unwrap();
+
+        let v = config.build_value(false).unwrap();
+
+        assert_eq!(v.get("name").try_into_string().unwrap(), "app");
+        assert_eq!(v.get("server").get("port").try_into_i64().unwrap(), 8080);
+    }
+
+    #[cfg(all(feature = "clap", feature = "toml"))]
+    #[test]
+    fn add_config_arg_layer() {
+        let path = "./target/test_config_arg.toml";
+        std::fs::write(path, "name = \"file\"\nport = 80\n").unwrap();
+
+        let command = ClapConfigLayer::with_config_arg(
+            clap::Command::new("app").arg(clap::Arg::new("port").long("port")),
+        );
+        let matches = command.get_matches_from(["app", "--config", path, "--port", "8080"]);
+
+        let config = BakuninConfig::new()
+            .with_config_arg_layer("file", &matches)
+            .unwrap()
+            .with_clap_layer("args", matches);
+
+        let v = config.build_value(false).unwrap();
+
+        assert_eq!(v.get("name").try_into_string().unwrap(), "file");
+        assert_eq!(v.get("port").try_into_i64().unwrap(), 8080);
+        assert!(!v.contains_path("config"));
+
+        let command = clap::Command::new("app").arg(clap::Arg::new("config").long("config"));
+        let matches = command.get_matches_from(["app", "--config", path]);
+        let err = BakuninConfig::new()
+            .with_config_arg_layer("file", &matches)
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("Invalid argument 'config'"),
+            "{}",
+            err
+        );
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn set_in_layer() {
+        let path = std::path::PathBuf::from("./target/test_set_in_layer.toml");
+        std::fs::write(&path, "# user settings\ntheme = \"dark\"\nfont = 12\n").unwrap();
+
+        let config = BakuninConfig::new()
+            .with_memory_layer("default", value_map! { theme: "light", font: 10 })
+            .with_file_layer("user", path.clone())
+            .unwrap();
+
+        config
+            .set_in_layer("user", "editor.tabs", Value::from(4))
+            .unwrap();
+        let removed = config.remove_in_layer("user", "font").unwrap();
+        assert_eq!(removed, Some(Value::from(12)));
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            content,
+            "# user settings\ntheme = \"dark\"\n\n[editor]\ntabs = 4\n"
+        );
+
+        let v = config.build_value(false).unwrap();
+        assert_eq!(v.get("font").try_into_i64().unwrap(), 10);
+
+        let err = config
+            .set_in_layer("default", "font", Value::from(14))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Config Layer Error: Writing to layer 'default' is not supported"
+        );
+
+        let err = config.remove_in_layer("missing", "font").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Config Layer Error: Layer 'missing' not found"
+        );
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn save_layer_delta() {
+        let path = std::path::PathBuf::from("./target/test_save_delta.toml");
+        std::fs::write(&path, "# font size\nfont = 14\ntheme = \"light\"\n").unwrap();
+        // Layers above the saved one are not read
+        let broken = std::path::PathBuf::from("./target/test_save_delta_broken.toml");
+        std::fs::write(&broken, "font = \n").unwrap();
+
+        let config = BakuninConfig::new()
+            .with_memory_layer("default", value_map! { theme: "light", font: 12, tabs: 4 })
+            .with_file_layer("user", path.clone())
+            .unwrap()
+            .with_memory_layer("session", value_map! { font: 20 })
+            .with_file_layer("broken", broken)
+            .unwrap()
+            .with_merge_options(MergeOptions::default().with_unset_sentinel(Some("!unset".into())))
+            .with_save_mode(SaveMode::Delta);
+
+        let value = value_map! { theme: "light", font: 16, tabs: 4 };
+        config.save_layer("user", &value).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content, "# font size\nfont = 16\n");
+
+        let value = value_map! { theme: "light", font: 12 };
+        config.save_layer("user", &value).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content, "tabs = \"!unset\"\n");
+
+        let v = config.build_value(true).unwrap();
+        assert_eq!(v.get("font").try_into_i64().unwrap(), 20);
+        assert!(!v.contains_path("tabs"));
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn save_to_write_target() {
+        let path = std::path::PathBuf::from("./target/test_write_target.toml");
+        let _ = std::fs::remove_file(&path);
+
+        let config = BakuninConfig::new()
+            .with_memory_layer("default", value_map! { theme: "light" })
+            .with_layer(
+                "global",
+                Box::new(FileConfigLayer::<handlers::TomlFileHandler>::read_only(
+                    std::path::PathBuf::from("./target/test_write_target_global.toml"),
+                )),
+            )
+            .with_file_layer("user", path.clone())
+            .unwrap()
+            .with_environment_layer("env", "TEST_WRITE_TARGET_");
+
+        assert_eq!(config.get_writable_layers(), vec!["user"]);
+        assert_eq!(config.get_write_target(), Some("user"));
+
+        config.save(&value_map! { theme: "dark" }).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "theme = \"dark\"\n"
+        );
+
+        let err = config
+            .set_in_layer("global", "theme", Value::from("dark"))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Config Layer Error: Writing to layer 'global' is not supported"
+        );
+
+        let config = BakuninConfig::new().with_memory_layer("default", Value::new_map());
+        assert_eq!(config.get_write_target(), None);
+        assert!(config.save(&value_map! { theme: "dark" }).is_err());
+    }
+
+    #[test]
+    fn build_value_removes_unset_keys() {
+        let config = BakuninConfig::new()
+            .with_memory_layer("default", value_map! { level: "info", port: 80 })
+            .with_memory_layer("local", value_map! { level: "!unset", port: Value::None });
+
+        let v = config.build_value(false).unwrap();
+        assert_eq!(v.get("level").try_into_string().unwrap(), "!unset");
+
+        let config = config
+            .with_merge_options(MergeOptions::default().with_unset_sentinel(Some("!unset".into())));
+        let v = config.build_value(false).unwrap();
+
+        assert!(!v.contains_path("level"));
+        assert!(v.contains_path("port"));
+        assert!(v.get("port").is_none());
+    }
+
+    #[test]
+    fn build_value_checks_schema() {
+        let schema = Schema::map().field("port", Schema::integer().required());
+
+        let config = BakuninConfig::new()
+            .with_memory_layer("default", value_map! { port: "none" })
+            .with_schema(schema.clone(), SchemaPolicy::Refuse);
+
+        let err = config.build_value(false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Model Error: Value doesn't match schema: port: expected Integer, found String"
+        );
+
+        assert_eq!(
+            config.explain("port").unwrap(),
+            "port = none\n  set by 'default' (Memory)"
+        );
+
+        let config = config.with_schema(schema, SchemaPolicy::Warn);
+        assert!(config.build_value(false).is_ok());
+    }
+}
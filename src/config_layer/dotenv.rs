@@ -0,0 +1,471 @@
+//! Parser and writer for dotenv (`.env`) files.
+//!
+//! Supports comments, the `export` prefix, single quoted values (literal), double quoted
+//! values (with escapes, and spanning lines) and `${VAR}` or `$VAR` expansion against
+//! variables defined in earlier lines.
+
+use std::collections::HashMap;
+
+use super::ConfigLayerError;
+
+/// A line of a dotenv file, variables with multi-line values span more than one line.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum DotenvLine {
+    /// Blank lines and comments, kept as written.
+    Text(String),
+    Var {
+        key: String,
+        value: String,
+        /// The text of the line, kept when the value isn't changed.
+        raw: String,
+    },
+}
+
+/// Parses the content of a dotenv file into its lines.
+pub(crate) fn parse(content: &str) -> Result<Vec<DotenvLine>, ConfigLayerError> {
+    let mut parser = Parser {
+        chars: content.chars().collect(),
+        pos: 0,
+        line: 1,
+        vars: HashMap::new(),
+    };
+
+    let mut lines = Vec::new();
+    while parser.pos < parser.chars.len() {
+        lines.push(parser.parse_line()?);
+    }
+
+    Ok(lines)
+}
+
+/// Returns the variables defined in `lines`, in order.
+pub(crate) fn vars(lines: &[DotenvLine]) -> Vec<(String, String)> {
+    lines
+        .iter()
+        .filter_map(|line| match line {
+            DotenvLine::Var { key, value, .. } => Some((key.clone(), value.clone())),
+            DotenvLine::Text(_) => None,
+        })
+        .collect()
+}
+
+/// Formats a variable as a dotenv line, quoting the value if needed.
+pub(crate) fn format_var(key: &str, value: &str) -> String {
+    let is_plain = value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "_-./:@,+%".contains(c));
+
+    if is_plain {
+        return format!("{}={}", key, value);
+    }
+
+    let mut quoted = String::with_capacity(value.len() + 2);
+    for c in value.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '$' => quoted.push_str("\\$"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+
+    format!("{}=\"{}\"", key, quoted)
+}
+
+/// Updates `lines` with `vars`, keeping comments, order and unchanged lines.
+///
+/// Variables for which `is_owned` returns true but are not in `vars` are removed,
+/// new variables are appended at the end.
+pub(crate) fn update(
+    lines: Vec<DotenvLine>,
+    vars: &[(String, String)],
+    is_owned: impl Fn(&str) -> bool,
+) -> Vec<DotenvLine> {
+    let mut written = Vec::new();
+    let mut result = Vec::with_capacity(lines.len() + vars.len());
+
+    for line in lines {
+        let (key, value) = match &line {
+            DotenvLine::Var { key, value, .. } => (key, value),
+            DotenvLine::Text(_) => {
+                result.push(line);
+                continue;
+            }
+        };
+
+        match vars.iter().find(|(k, _)| k == key) {
+            Some((_, new_value)) if written.contains(key) => {
+                log::trace!("Removing duplicated dotenv variable {}={}", key, new_value);
+            }
+            Some((_, new_value)) if new_value == value => {
+                written.push(key.clone());
+                result.push(line);
+            }
+            Some((_, new_value)) => {
+                written.push(key.clone());
+                result.push(DotenvLine::Var {
+                    key: key.clone(),
+                    value: new_value.clone(),
+                    raw: format_var(key, new_value),
+                });
+            }
+            None if is_owned(key) => {}
+            None => result.push(line),
+        }
+    }
+
+    for (key, value) in vars {
+        if !written.contains(key) {
+            result.push(DotenvLine::Var {
+                key: key.clone(),
+                value: value.clone(),
+                raw: format_var(key, value),
+            });
+        }
+    }
+
+    result
+}
+
+/// Converts lines back to the content of a dotenv file.
+pub(crate) fn to_string(lines: &[DotenvLine]) -> String {
+    let mut content = String::new();
+    for line in lines {
+        match line {
+            DotenvLine::Text(text) => content.push_str(text),
+            DotenvLine::Var { raw, .. } => content.push_str(raw),
+        }
+        content.push('\n');
+    }
+    content
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    /// Variables defined so far, used for expansion.
+    vars: HashMap<String, String>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn error(&self, message: &str) -> ConfigLayerError {
+        ConfigLayerError::ParseError(format!("line {}: {}", self.line, message))
+    }
+
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(' ') | Some('\t')) {
+            self.next();
+        }
+    }
+
+    /// Text from `start` to the current position, without the line break.
+    fn raw_from(&self, start: usize) -> String {
+        let raw: String = self.chars[start..self.pos].iter().collect();
+        raw.trim_end_matches(['\n', '\r']).to_string()
+    }
+
+    /// Consumes the rest of the line, returning it.
+    fn rest_of_line(&mut self) -> String {
+        let mut rest = String::new();
+        while let Some(c) = self.next() {
+            if c == '\n' {
+                break;
+            }
+            rest.push(c);
+        }
+        rest
+    }
+
+    fn parse_line(&mut self) -> Result<DotenvLine, ConfigLayerError> {
+        let start = self.pos;
+        self.skip_spaces();
+
+        if matches!(self.peek(), None | Some('#') | Some('\n') | Some('\r')) {
+            self.rest_of_line();
+            return Ok(DotenvLine::Text(self.raw_from(start)));
+        }
+
+        let mut key = self.parse_key();
+        if key == "export" && matches!(self.peek(), Some(' ') | Some('\t')) {
+            self.skip_spaces();
+            key = self.parse_key();
+        }
+
+        if key.is_empty() {
+            return Err(self.error("expected a variable name"));
+        }
+
+        self.skip_spaces();
+        if self.peek() != Some('=') {
+            return Err(self.error(&format!("expected '=' after {}", key)));
+        }
+        self.next();
+        self.skip_spaces();
+
+        let value = match self.peek() {
+            Some('"') => {
+                self.next();
+                let value = self.parse_double_quoted()?;
+                self.parse_line_end()?;
+                value
+            }
+            Some('\'') => {
+                self.next();
+                let value = self.parse_single_quoted()?;
+                self.parse_line_end()?;
+                value
+            }
+            _ => self.parse_unquoted(),
+        };
+
+        self.vars.insert(key.clone(), value.clone());
+        Ok(DotenvLine::Var {
+            key,
+            value,
+            raw: self.raw_from(start),
+        })
+    }
+
+    fn parse_key(&mut self) -> String {
+        let mut key = String::new();
+        while let Some(c) = self.peek() {
+            if !(c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-') {
+                break;
+            }
+            key.push(c);
+            self.next();
+        }
+        key
+    }
+
+    /// After a quoted value only spaces and a comment are allowed.
+    fn parse_line_end(&mut self) -> Result<(), ConfigLayerError> {
+        let error = self.error("unexpected text after quoted value");
+        let rest = self.rest_of_line();
+        let rest = rest.trim();
+        if rest.is_empty() || rest.starts_with('#') {
+            Ok(())
+        } else {
+            Err(error)
+        }
+    }
+
+    fn parse_single_quoted(&mut self) -> Result<String, ConfigLayerError> {
+        let mut value = String::new();
+        loop {
+            match self.next() {
+                Some('\'') => return Ok(value),
+                Some(c) => value.push(c),
+                None => return Err(self.error("unterminated single quoted value")),
+            }
+        }
+    }
+
+    fn parse_double_quoted(&mut self) -> Result<String, ConfigLayerError> {
+        let mut value = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(value),
+                Some('\\') => match self.next() {
+                    Some('n') => value.push('\n'),
+                    Some('r') => value.push('\r'),
+                    Some('t') => value.push('\t'),
+                    Some(c @ ('"' | '\\' | '$' | '\'')) => value.push(c),
+                    Some(c) => {
+                        value.push('\\');
+                        value.push(c);
+                    }
+                    None => return Err(self.error("unterminated double quoted value")),
+                },
+                Some('$') => self.expand(&mut value),
+                Some(c) => value.push(c),
+                None => return Err(self.error("unterminated double quoted value")),
+            }
+        }
+    }
+
+    fn parse_unquoted(&mut self) -> String {
+        let rest = self.rest_of_line();
+        let rest = rest.trim_end_matches('\r');
+
+        // A `#` starts a comment only after a space, so `a#b` is a value
+        let mut end = rest.len();
+        let mut previous = ' ';
+        for (i, c) in rest.char_indices() {
+            if c == '#' && (previous == ' ' || previous == '\t') {
+                end = i;
+                break;
+            }
+            previous = c;
+        }
+
+        let chars: Vec<char> = rest[..end].trim().chars().collect();
+        let mut value = String::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            i += 1;
+            if c != '$' {
+                value.push(c);
+                continue;
+            }
+
+            match read_var_name(&chars[i..]) {
+                Some((name, len)) => {
+                    i += len;
+                    value.push_str(self.lookup(&name));
+                }
+                None => value.push('$'),
+            }
+        }
+
+        value
+    }
+
+    /// Expands a variable after a `$` in a double quoted value.
+    fn expand(&mut self, value: &mut String) {
+        match read_var_name(&self.chars[self.pos..]) {
+            Some((name, len)) => {
+                self.pos += len;
+                value.push_str(self.lookup(&name));
+            }
+            None => value.push('$'),
+        }
+    }
+
+    fn lookup(&self, name: &str) -> &str {
+        match self.vars.get(name) {
+            Some(value) => value,
+            None => {
+                log::debug!("Dotenv variable {} is not defined, expanding to ''", name);
+                ""
+            }
+        }
+    }
+}
+
+/// Reads a variable name after a `$`, either `{NAME}` or `NAME`,
+/// returning it with the number of characters read.
+fn read_var_name(chars: &[char]) -> Option<(String, usize)> {
+    let braced = chars.first() == Some(&'{');
+    let start = usize::from(braced);
+
+    let name: String = chars[start..]
+        .iter()
+        .enumerate()
+        .take_while(|(i, c)| {
+            c.is_ascii_alphanumeric() && !(*i == 0 && c.is_ascii_digit()) || **c == '_'
+        })
+        .map(|(_, c)| *c)
+        .collect();
+
+    let mut len = start + name.len();
+    if name.is_empty() {
+        return None;
+    }
+    if braced {
+        if chars.get(len) != Some(&'}') {
+            return None;
+        }
+        len += 1;
+    }
+
+    Some((name, len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_vars(content: &str) -> Vec<(String, String)> {
+        vars(&parse(content).unwrap())
+    }
+
+    fn pair(key: &str, value: &str) -> (String, String) {
+        (key.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn test_parse_dotenv() {
+        let content = r#"
+# comment
+export APP_NAME=app
+APP_PLAIN = value with spaces # comment
+APP_HASH=a#b
+APP_SINGLE='literal $APP_NAME \n'
+APP_DOUBLE="line\nnext \"quoted\" \$APP_NAME"
+APP_EXPAND=${APP_NAME}-$APP_NAME-${MISSING}
+APP_MULTI="first
+second" # trailing
+APP_EMPTY=
+"#;
+
+        assert_eq!(
+            parse_vars(content),
+            vec![
+                pair("APP_NAME", "app"),
+                pair("APP_PLAIN", "value with spaces"),
+                pair("APP_HASH", "a#b"),
+                pair("APP_SINGLE", "literal $APP_NAME \\n"),
+                pair("APP_DOUBLE", "line\nnext \"quoted\" $APP_NAME"),
+                pair("APP_EXPAND", "app-app-"),
+                pair("APP_MULTI", "first\nsecond"),
+                pair("APP_EMPTY", ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_dotenv_errors() {
+        let err = parse("A=1\nB\n").unwrap_err().to_string();
+        assert!(err.contains("line 2"), "{}", err);
+
+        assert!(parse("A=\"open\n").is_err());
+        assert!(parse("A='open\n").is_err());
+        assert!(parse("A=\"x\" y\n").is_err());
+    }
+
+    #[test]
+    fn test_update_keeps_comments() {
+        let content = "# settings\nAPP_A=1 # keep\nAPP_B=2\nOTHER=x\n\nAPP_C='c'\n";
+        let lines = parse(content).unwrap();
+
+        let vars = vec![
+            pair("APP_A", "1"),
+            pair("APP_C", "new value"),
+            pair("APP_D", "d"),
+        ];
+        let lines = update(lines, &vars, |k| k.starts_with("APP_"));
+
+        assert_eq!(
+            to_string(&lines),
+            "# settings\nAPP_A=1 # keep\nOTHER=x\n\nAPP_C=\"new value\"\nAPP_D=d\n"
+        );
+        assert_eq!(
+            parse_vars(&to_string(&lines)),
+            vec![
+                pair("APP_A", "1"),
+                pair("OTHER", "x"),
+                pair("APP_C", "new value"),
+                pair("APP_D", "d")
+            ]
+        );
+    }
+}
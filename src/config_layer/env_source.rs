@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+
+/// Source of environment variables read by `EnvironmentConfigLayer`.
+///
+/// Allows layers to read from something other than the process environment,
+/// like a fixed map in tests.
+pub trait EnvSource: std::fmt::Debug {
+    /// Returns all variables, as `(name, value)` pairs.
+    fn vars(&self) -> Vec<(OsString, OsString)>;
+}
+
+/// Reads the environment of the current process every time it's used.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcessEnv;
+
+impl EnvSource for ProcessEnv {
+    fn vars(&self) -> Vec<(OsString, OsString)> {
+        std::env::vars_os().collect()
+    }
+}
+
+/// Copy of the environment of the current process, or of another `EnvSource`,
+/// taken when it's captured.
+///
+/// Later changes to the source are not seen.
+#[derive(Debug, Clone, Default)]
+pub struct EnvSnapshot {
+    vars: Vec<(OsString, OsString)>,
+}
+
+impl EnvSnapshot {
+    /// Captures the current environment of the process.
+    pub fn capture() -> Self {
+        Self::capture_from(&ProcessEnv)
+    }
+
+    /// Captures the variables of the current process that start with `prefix`.
+    pub fn capture_prefix(prefix: &str) -> Self {
+        Self::capture_prefix_from(&ProcessEnv, prefix)
+    }
+
+    /// Captures the current variables of `source`.
+    pub fn capture_from<S: EnvSource + ?Sized>(source: &S) -> Self {
+        Self {
+            vars: source.vars(),
+        }
+    }
+
+    /// Captures the variables of `source` that start with `prefix`.
+    pub fn capture_prefix_from<S: EnvSource + ?Sized>(source: &S, prefix: &str) -> Self {
+        Self {
+            vars: source
+                .vars()
+                .into_iter()
+                .filter(|(key, _)| key.to_str().is_some_and(|k| k.starts_with(prefix)))
+                .collect(),
+        }
+    }
+}
+
+impl EnvSource for EnvSnapshot {
+    fn vars(&self) -> Vec<(OsString, OsString)> {
+        self.vars.clone()
+    }
+}
+
+impl EnvSource for HashMap<String, String> {
+    fn vars(&self) -> Vec<(OsString, OsString)> {
+        self.iter()
+            .map(|(key, value)| (key.into(), value.into()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_ignores_later_changes() {
+        let mut env = HashMap::from([
+            ("APP_before".to_string(), "1".to_string()),
+            ("OTHER".to_string(), "0".to_string()),
+        ]);
+        let snapshot = EnvSnapshot::capture_prefix_from(&env, "APP_");
+        env.insert("APP_after".to_string(), "2".to_string());
+
+        assert_eq!(snapshot.vars(), vec![("APP_before".into(), "1".into())]);
+        assert_eq!(EnvSnapshot::capture_from(&env).vars().len(), 3);
+    }
+}
@@ -2,8 +2,8 @@ use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum ConfigLayerError {
-    #[error("Writing to this layer is not supported")]
-    WriteNotSupported,
+    #[error("Writing to {} is not supported", describe_layer(.0))]
+    WriteNotSupported(Option<String>),
 
     #[error("Failed to read value: {0}")]
     ErrorReadingValue(String),
@@ -22,4 +22,36 @@ pub enum ConfigLayerError {
 
     #[error("Unsupported extension: {0}")]
     UnsupportedExtension(String),
+
+    #[error("Invalid argument '{arg}': {why}")]
+    InvalidArgument { arg: String, why: String },
+
+    #[error("Failed to read file '{}' from environment variable {var}: {source}", path.display())]
+    ErrorReadingFile {
+        var: String,
+        path: std::path::PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Backup not found: {}", .0.display())]
+    BackupNotFound(std::path::PathBuf),
+
+    #[error("Timed out after {timeout:?} waiting for the lock on '{}'", path.display())]
+    LockTimeout {
+        path: std::path::PathBuf,
+        timeout: std::time::Duration,
+    },
+
+    #[error("'{}' was modified since it was read", .0.display())]
+    StaleWrite(std::path::PathBuf),
+
+    #[error("Layer '{0}' not found")]
+    LayerNotFound(String),
+}
+
+fn describe_layer(name: &Option<String>) -> String {
+    match name {
+        Some(name) => format!("layer '{}'", name),
+        None => "this layer".to_string(),
+    }
 }
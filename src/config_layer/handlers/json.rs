@@ -1,21 +1,47 @@
-use crate::config_layer::{ConfigLayerError, Result};
+use crate::config_layer::ConfigLayerError;
+use crate::Result;
 
+/// Handler for strict JSON files, values are written pretty-printed.
 pub struct JsonFileHandler;
 
 impl crate::config_layer::FileHandler for JsonFileHandler {
     fn read(path: &std::path::PathBuf) -> Result<crate::Value> {
         log::trace!("Reading JSON file: {}", path.display());
         let content = std::fs::read_to_string(path)?;
-        json5::from_str(&content).map_err(|e| ConfigLayerError::ParseError(e.to_string()))
+        serde_json::from_str(&content)
+            .map_err(|e| ConfigLayerError::ParseError(e.to_string()).into())
     }
 
     fn write(path: &std::path::PathBuf, value: &crate::Value) -> Result<()> {
         log::trace!("Writing JSON file: {}", path.display());
-        let content = json5::to_string(value)
+        let content = serde_json::to_string_pretty(value)
             .map_err(|e| ConfigLayerError::SerializeError(e.to_string()))?;
 
         std::fs::write(path, content)?;
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config_layer::FileHandler;
+    use crate::value_map;
+
+    #[test]
+    fn test_json_write_pretty_and_read() {
+        let path = std::path::PathBuf::from("./target/test_json_handler.json");
+        let value = value_map! { name: "app", port: 8080 };
+
+        JsonFileHandler::write(&path, &value).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains("\n  \"port\": 8080"));
+        let read = JsonFileHandler::read(&path).unwrap();
+        assert_eq!(read.get("name").try_into_string().unwrap(), "app");
+        assert_eq!(read.get("port").try_into_i64().unwrap(), 8080);
+
+        std::fs::write(&path, "{ name: 'app', }").unwrap();
+        assert!(JsonFileHandler::read(&path).is_err());
+    }
+}
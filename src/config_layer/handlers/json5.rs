@@ -0,0 +1,50 @@
+use crate::config_layer::ConfigLayerError;
+use crate::Result;
+
+/// Handler for JSON5 and JSONC files.
+///
+/// Reading accepts comments, trailing commas, unquoted keys and the rest of JSON5.
+/// Since JSON is valid JSON5, values are written as pretty-printed JSON, comments are not kept.
+pub struct Json5FileHandler;
+
+impl crate::config_layer::FileHandler for Json5FileHandler {
+    fn read(path: &std::path::PathBuf) -> Result<crate::Value> {
+        log::trace!("Reading JSON5 file: {}", path.display());
+        let content = std::fs::read_to_string(path)?;
+        json5::from_str(&content).map_err(|e| ConfigLayerError::ParseError(e.to_string()).into())
+    }
+
+    fn write(path: &std::path::PathBuf, value: &crate::Value) -> Result<()> {
+        log::trace!("Writing JSON5 file: {}", path.display());
+        let content = serde_json::to_string_pretty(value)
+            .map_err(|e| ConfigLayerError::SerializeError(e.to_string()))?;
+
+        std::fs::write(path, content)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config_layer::FileHandler;
+
+    #[test]
+    fn test_json5_read_relaxed_syntax() {
+        let path = std::path::PathBuf::from("./target/test_json5_handler.jsonc");
+        std::fs::write(
+            &path,
+            "// app settings\n{\n  name: 'app', /* inline */\n  port: 8080,\n  tags: [\"a\", \"b\",],\n}\n",
+        )
+        .unwrap();
+
+        let value = Json5FileHandler::read(&path).unwrap();
+        assert_eq!(value.get("name").try_into_string().unwrap(), "app");
+        assert_eq!(value.get("port").try_into_i64().unwrap(), 8080);
+        assert_eq!(value.get("tags").len(), 2);
+
+        Json5FileHandler::write(&path, &value).unwrap();
+        assert_eq!(Json5FileHandler::read(&path).unwrap(), value);
+    }
+}
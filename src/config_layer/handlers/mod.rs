@@ -4,8 +4,20 @@ mod toml;
 #[cfg(feature = "json")]
 mod json;
 
+#[cfg(feature = "json5")]
+mod json5;
+
+#[cfg(feature = "yaml")]
+mod yaml;
+
 #[cfg(feature = "toml")]
 pub use toml::TomlFileHandler;
 
 #[cfg(feature = "json")]
-pub use json::JsonFileHandler;
\ No newline at end of file
+pub use json::JsonFileHandler;
+
+#[cfg(feature = "json5")]
+pub use json5::Json5FileHandler;
+
+#[cfg(feature = "yaml")]
+pub use yaml::YamlFileHandler;
@@ -1,5 +1,9 @@
+use serde::de::IntoDeserializer;
+use serde::{Deserialize, Serialize};
+use toml_edit::{ArrayOfTables, DocumentMut, Item, Table, TableLike};
+
 use crate::config_layer::ConfigLayerError;
-use crate::Result;
+use crate::{Result, Value};
 
 pub struct TomlFileHandler;
 
@@ -10,13 +14,276 @@ impl crate::config_layer::FileHandler for TomlFileHandler {
         toml::from_str(&content).map_err(|e| ConfigLayerError::ParseError(e.to_string()).into())
     }
 
+    /// Writes the value to the file. If the file exists, only the keys that changed are
+    /// edited, keeping the comments, order and formatting of the rest of the document.
     fn write(path: &std::path::PathBuf, value: &crate::Value) -> Result<()> {
         log::trace!("Writing TOML file: {}", path.display());
-        let content = toml::to_string_pretty(value)
-            .map_err(|e| ConfigLayerError::SerializeError(e.to_string()))?;
+        let content = if path.exists() {
+            let content = std::fs::read_to_string(path)?;
+            update_document(&content, value)?
+        } else {
+            toml::to_string_pretty(value)
+                .map_err(|e| ConfigLayerError::SerializeError(e.to_string()))?
+        };
 
         std::fs::write(path, content)?;
 
         Ok(())
     }
 }
+
+/// Edits the TOML document in `content` to match `value`, adding, changing and removing
+/// only the keys that differ.
+fn update_document(content: &str, value: &Value) -> Result<String> {
+    let mut document: DocumentMut = content
+        .parse()
+        .map_err(|e: toml_edit::TomlError| ConfigLayerError::ParseError(e.to_string()))?;
+
+    match value {
+        Value::Map(_) => update_table(document.as_table_mut(), value)?,
+        _ => {
+            return Err(
+                ConfigLayerError::SerializeError("TOML root must be a table".to_string()).into(),
+            )
+        }
+    }
+
+    Ok(document.to_string())
+}
+
+fn update_table(table: &mut dyn TableLike, value: &Value) -> Result<()> {
+    let map = match value {
+        Value::Map(map) => map,
+        _ => unreachable!("only maps are written as tables"),
+    };
+
+    let removed: Vec<String> = table
+        .iter()
+        .map(|(key, _)| key.to_string())
+        .filter(|key| map.get(key).is_none_or(|v| v.is_none()))
+        .collect();
+    for key in removed {
+        table.remove(&key);
+    }
+
+    let mut keys: Vec<&String> = map.keys().collect();
+    keys.sort();
+
+    for key in keys {
+        let value = &map[key];
+        if value.is_none() {
+            continue;
+        }
+
+        match table.get_mut(key) {
+            Some(item) => update_item(item, value)?,
+            None => {
+                table.insert(key, new_item(value)?);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn update_item(item: &mut Item, value: &Value) -> Result<()> {
+    match (&mut *item, value) {
+        (Item::Table(table), Value::Map(_)) => update_table(table, value),
+        (Item::ArrayOfTables(array), Value::Array(values))
+            if values.iter().all(|v| matches!(v, Value::Map(_))) =>
+        {
+            update_array_of_tables(array, values)
+        }
+        (Item::Value(current), _) if current.is_inline_table() || !value.is_map() => {
+            update_value(current, value)
+        }
+        _ => {
+            *item = new_item(value)?;
+            Ok(())
+        }
+    }
+}
+
+fn update_array_of_tables(array: &mut ArrayOfTables, values: &[Value]) -> Result<()> {
+    while array.len() > values.len() {
+        array.remove(array.len() - 1);
+    }
+
+    for (i, value) in values.iter().enumerate() {
+        match array.get_mut(i) {
+            Some(table) => update_table(table, value)?,
+            None => array.push(new_table(value)?),
+        }
+    }
+
+    Ok(())
+}
+
+fn update_array(array: &mut toml_edit::Array, values: &[Value]) -> Result<()> {
+    while array.len() > values.len() {
+        array.remove(array.len() - 1);
+    }
+
+    for (i, value) in values.iter().enumerate() {
+        match array.get_mut(i) {
+            Some(current) => update_value(current, value)?,
+            None => array.push_formatted(to_toml_value(value)?),
+        }
+    }
+
+    Ok(())
+}
+
+/// Replaces `current` if it's different from `value`, keeping its comments and whitespace.
+/// Arrays and inline tables are updated element by element.
+fn update_value(current: &mut toml_edit::Value, value: &Value) -> Result<()> {
+    match (&mut *current, value) {
+        (toml_edit::Value::InlineTable(table), Value::Map(_)) => return update_table(table, value),
+        (toml_edit::Value::Array(array), Value::Array(values)) => {
+            return update_array(array, values)
+        }
+        _ => (),
+    }
+
+    let new = to_toml_value(value)?;
+    if same_value(current, &new) {
+        return Ok(());
+    }
+
+    let decor = current.decor().clone();
+    *current = new;
+    *current.decor_mut() = decor;
+
+    Ok(())
+}
+
+/// Compares two TOML values by their content, ignoring formatting, like `0x10` and `16`,
+/// and the order of the keys of inline tables.
+fn same_value(a: &toml_edit::Value, b: &toml_edit::Value) -> bool {
+    let parse = |value: &toml_edit::Value| {
+        <Value as Deserialize>::deserialize(value.clone().into_deserializer()).ok()
+    };
+
+    parse(a).is_some_and(|a| Some(a) == parse(b))
+}
+
+/// Creates an item for a new key, maps are written as tables and arrays of maps as
+/// arrays of tables.
+fn new_item(value: &Value) -> Result<Item> {
+    match value {
+        Value::Map(_) => Ok(Item::Table(new_table(value)?)),
+        Value::Array(values)
+            if !values.is_empty() && values.iter().all(|v| matches!(v, Value::Map(_))) =>
+        {
+            let mut array = ArrayOfTables::new();
+            for value in values {
+                array.push(new_table(value)?);
+            }
+            Ok(Item::ArrayOfTables(array))
+        }
+        _ => Ok(Item::Value(to_toml_value(value)?)),
+    }
+}
+
+fn new_table(value: &Value) -> Result<Table> {
+    let mut table = Table::new();
+    update_table(&mut table, value)?;
+    Ok(table)
+}
+
+fn to_toml_value(value: &Value) -> Result<toml_edit::Value> {
+    value
+        .serialize(toml_edit::ser::ValueSerializer::new())
+        .map_err(|e| ConfigLayerError::SerializeError(e.to_string()).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config_layer::FileHandler;
+    use crate::{value_map, value_vec};
+
+    #[test]
+    fn test_toml_write_keeps_formatting() {
+        let path = std::path::PathBuf::from("./target/test_toml_write_edit.toml");
+        std::fs::write(
+            &path,
+            r#"# Application settings
+name = 'app' # the name
+mask = 0x10
+
+[log]
+# one of: debug, info
+level = "info"
+file = "app.log"
+
+[[servers]]
+host = "a"
+
+[[servers]]
+host = "b"
+"#,
+        )
+        .unwrap();
+
+        let value = value_map! {
+            name: "app",
+            mask: 16,
+            log: value_map! { level: "debug" },
+            servers: value_vec![value_map! { host: "a" }],
+            ports: value_vec![80, 443],
+            db: value_map! { url: "postgres://db" },
+        };
+        TomlFileHandler::write(&path, &value).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            content,
+            r#"# Application settings
+name = 'app' # the name
+mask = 0x10
+ports = [80, 443]
+
+[log]
+# one of: debug, info
+level = "debug"
+
+[[servers]]
+host = "a"
+
+[db]
+url = "postgres://db"
+"#
+        );
+
+        let read = TomlFileHandler::read(&path).unwrap();
+        assert_eq!(read.get("log"), value.get("log"));
+        assert_eq!(read.get("ports").len(), 2);
+        assert_eq!(read.get("db"), value.get("db"));
+    }
+
+    #[test]
+    fn test_toml_write_keeps_unchanged_arrays_and_inline_tables() {
+        let path = std::path::PathBuf::from("./target/test_toml_write_nested.toml");
+        let content = r#"matrix = [ [1, 0x2],  [3, 4] ]
+points = [ { y = 2, x = 1 },  { x = 3, y = 4 } ]
+owner = {  name = "me" }
+"#;
+        std::fs::write(&path, content).unwrap();
+
+        let value = TomlFileHandler::read(&path).unwrap();
+        TomlFileHandler::write(&path, &value).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), content);
+
+        let mut value = value;
+        value.set_path("points[1].y", Value::from(5)).unwrap();
+        TomlFileHandler::write(&path, &value).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            r#"matrix = [ [1, 0x2],  [3, 4] ]
+points = [ { y = 2, x = 1 },  { x = 3, y = 5 } ]
+owner = {  name = "me" }
+"#
+        );
+    }
+}
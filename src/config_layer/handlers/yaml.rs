@@ -0,0 +1,107 @@
+use serde::Deserialize;
+
+use crate::config_layer::ConfigLayerError;
+use crate::Result;
+
+/// Handler for YAML files.
+///
+/// Anchors and aliases are resolved, and merge keys (`<<: *anchor`) are applied.
+/// Files with more than one document are rejected, since a layer holds a single value.
+pub struct YamlFileHandler;
+
+impl crate::config_layer::FileHandler for YamlFileHandler {
+    fn read(path: &std::path::PathBuf) -> Result<crate::Value> {
+        log::trace!("Reading YAML file: {}", path.display());
+        let content = std::fs::read_to_string(path)?;
+
+        let mut documents = serde_yaml_ng::Deserializer::from_str(&content)
+            .map(serde_yaml_ng::Value::deserialize)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| ConfigLayerError::ParseError(e.to_string()))?;
+
+        if documents.len() > 1 {
+            return Err(ConfigLayerError::ParseError(format!(
+                "{} contains {} YAML documents, only one is supported",
+                path.display(),
+                documents.len()
+            ))
+            .into());
+        }
+
+        let mut document = documents.pop().unwrap_or(serde_yaml_ng::Value::Null);
+        if document.is_null() {
+            return Ok(crate::Value::Map(Default::default()));
+        }
+
+        document
+            .apply_merge()
+            .map_err(|e| ConfigLayerError::ParseError(e.to_string()))?;
+
+        serde_yaml_ng::from_value(document)
+            .map_err(|e| ConfigLayerError::ParseError(e.to_string()).into())
+    }
+
+    fn write(path: &std::path::PathBuf, value: &crate::Value) -> Result<()> {
+        log::trace!("Writing YAML file: {}", path.display());
+        let content = serde_yaml_ng::to_string(value)
+            .map_err(|e| ConfigLayerError::SerializeError(e.to_string()))?;
+
+        std::fs::write(path, content)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config_layer::FileHandler;
+    use crate::{value_map, Value};
+
+    fn write_file(name: &str, content: &str) -> std::path::PathBuf {
+        let path = std::path::PathBuf::from(format!("./target/{}", name));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_yaml_resolves_anchors() {
+        let path = write_file(
+            "test_yaml_anchors.yaml",
+            "base: &base\n  host: localhost\n  port: 80\nprod:\n  <<: *base\n  port: 443\nhosts: [*base]\n",
+        );
+
+        let value = YamlFileHandler::read(&path).unwrap();
+        assert_eq!(
+            value.get("prod").get("host").try_into_string().unwrap(),
+            "localhost"
+        );
+        assert_eq!(value.get("prod").get("port").try_into_i64().unwrap(), 443);
+        let port = value.get_path("hosts[0].port").unwrap().unwrap();
+        assert_eq!(port.try_into_i64().unwrap(), 80);
+    }
+
+    #[test]
+    fn test_yaml_rejects_multiple_documents() {
+        let path = write_file("test_yaml_multi.yml", "a: 1\n---\nb: 2\n");
+
+        let err = YamlFileHandler::read(&path).unwrap_err();
+        assert!(
+            err.to_string().contains("contains 2 YAML documents"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_yaml_write_and_read() {
+        let path = std::path::PathBuf::from("./target/test_yaml_write.yaml");
+        let value = value_map! { name: "app", debug: true };
+
+        YamlFileHandler::write(&path, &value).unwrap();
+        assert_eq!(YamlFileHandler::read(&path).unwrap(), value);
+
+        let path = write_file("test_yaml_empty.yaml", "# nothing here\n");
+        assert_eq!(YamlFileHandler::read(&path).unwrap(), Value::new_map());
+    }
+}
@@ -0,0 +1,136 @@
+use crate::Value;
+
+/// Parses a string from a text source, like an environment variable, into a typed value.
+///
+/// Recognizes booleans, `null`, integers, floats and, when the `json` or `toml` features are
+/// enabled, inline arrays and tables like `[1, 2]` or `{ "a": 1 }`. Numbers with leading
+/// zeros, like `007`, are kept as strings. Anything else is returned as `Value::String`.
+pub(crate) fn infer_value(value: &str) -> Value {
+    let trimmed = value.trim();
+
+    if trimmed.eq_ignore_ascii_case("true") {
+        return Value::Boolean(true);
+    }
+    if trimmed.eq_ignore_ascii_case("false") {
+        return Value::Boolean(false);
+    }
+    if trimmed.eq_ignore_ascii_case("null") {
+        return Value::None;
+    }
+
+    if let Some(number) = infer_number(trimmed) {
+        return number;
+    }
+
+    if trimmed.starts_with('[') || trimmed.starts_with('{') {
+        if let Some(value) = infer_inline(trimmed) {
+            return value;
+        }
+    }
+
+    Value::String(value.to_string())
+}
+
+fn infer_number(value: &str) -> Option<Value> {
+    let digits = value.strip_prefix(['-', '+']).unwrap_or(value);
+    if !digits.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let integer_part = digits.split(['.', 'e', 'E']).next().unwrap_or_default();
+    if integer_part.len() > 1 && integer_part.starts_with('0') {
+        return None;
+    }
+
+    if digits.bytes().all(|b| b.is_ascii_digit()) {
+        if let Ok(v) = value.parse::<i64>() {
+            return Some(Value::Integer(v));
+        }
+        if let Ok(v) = value.parse::<u64>() {
+            return Some(Value::UInteger(v));
+        }
+        if let Ok(v) = value.parse::<i128>() {
+            return Some(Value::LongInteger(v));
+        }
+        if let Ok(v) = value.parse::<u128>() {
+            return Some(Value::ULongInteger(v));
+        }
+        return None;
+    }
+
+    if digits
+        .bytes()
+        .all(|b| b.is_ascii_digit() || matches!(b, b'.' | b'e' | b'E' | b'+' | b'-'))
+    {
+        return value.parse::<f64>().ok().map(Value::Float);
+    }
+
+    None
+}
+
+#[allow(unused_variables)]
+fn infer_inline(value: &str) -> Option<Value> {
+    #[cfg(feature = "toml")]
+    {
+        if let Ok(mut table) = toml::from_str::<Value>(&format!("value = {}", value)) {
+            return table.remove_path("value").ok().flatten();
+        }
+    }
+
+    #[cfg(feature = "json")]
+    {
+        if let Ok(value) = serde_json::from_str::<Value>(value) {
+            return Some(value);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infer_scalars() {
+        assert_eq!(infer_value("true"), Value::Boolean(true));
+        assert_eq!(infer_value("FALSE"), Value::Boolean(false));
+        assert_eq!(infer_value("null"), Value::None);
+        assert_eq!(infer_value("8080"), Value::Integer(8080));
+        assert_eq!(infer_value("-12"), Value::Integer(-12));
+        assert_eq!(
+            infer_value("18446744073709551615"),
+            Value::UInteger(u64::MAX)
+        );
+        assert_eq!(infer_value("2.5"), Value::Float(2.5));
+        assert_eq!(infer_value("1e3"), Value::Float(1000.0));
+        assert_eq!(infer_value("0"), Value::Integer(0));
+        assert_eq!(infer_value("0.5"), Value::Float(0.5));
+
+        assert_eq!(infer_value("007"), Value::from("007"));
+        assert_eq!(infer_value("inf"), Value::from("inf"));
+        assert_eq!(infer_value("1.2.3"), Value::from("1.2.3"));
+        assert_eq!(infer_value("v1"), Value::from("v1"));
+        assert_eq!(infer_value(" text "), Value::from(" text "));
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_infer_inline_toml() {
+        use crate::{value_map, value_vec};
+
+        assert_eq!(infer_value("[1, 2]"), value_vec![1, 2]);
+        assert_eq!(
+            infer_value("{ level = \"debug\", color = true }"),
+            value_map! { level: "debug", color: true }
+        );
+        assert_eq!(infer_value("[unclosed"), Value::from("[unclosed"));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_infer_inline_json() {
+        let value = infer_value("{\"hosts\": [\"a\", \"b\"]}");
+        assert_eq!(value.get("hosts").len(), 2);
+    }
+}
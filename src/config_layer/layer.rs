@@ -1,3 +1,4 @@
+use crate::model::ValuePath;
 use crate::{Result, Value};
 
 pub trait ConfigLayer {
@@ -10,4 +11,18 @@ pub trait ConfigLayer {
     fn can_write(&self) -> bool;
 
     fn write_value(&self, value: &Value) -> Result<()>;
+
+    /// Reads the value, applies `update` and writes it back. Layers that can be changed by
+    /// other processes, like files, override it to hold a lock during the whole update.
+    fn update_value(&self, update: &mut dyn FnMut(&mut Value) -> Result<()>) -> Result<()> {
+        let mut value = self.read_value()?;
+        update(&mut value)?;
+        self.write_value(&value)
+    }
+
+    /// Describes where the value at `path` is read from, like a file path or
+    /// an environment variable name. Used to report the origin of values.
+    fn get_source(&self, _path: &ValuePath) -> Option<String> {
+        None
+    }
 }
@@ -0,0 +1,296 @@
+use std::ffi::OsString;
+
+use super::infer::infer_value;
+use super::ConfigLayerError;
+use crate::model::ValuePath;
+use crate::{Result, Value};
+
+/// Configuration layer that reads values from command-line arguments.
+///
+/// Supported forms, where keys are paths like `log.level` or `hosts[0]`:
+/// - `--key=value` and `--key value`, the next argument is the value if it doesn't start
+///   with `-` or is a negative number, like `--offset -5`
+/// - `--key`, sets `true`, and `--no-key`, sets `false`
+/// - `-o key=value`
+///
+/// Repeating a key creates an array. Positional arguments, other short flags and anything
+/// after `--` are ignored.
+///
+/// A flag followed by a positional argument takes it as its value, so `--verbose input.txt`
+/// sets `verbose` to `"input.txt"`. Use `--verbose=true`, or pass positional arguments
+/// before the flags or after `--`, to keep them apart.
+#[derive(Debug, Clone)]
+pub struct ArgsConfigLayer {
+    args: Vec<OsString>,
+    infer_types: bool,
+}
+
+impl ArgsConfigLayer {
+    /// Creates a layer for the arguments of the current process, without the program name.
+    pub fn new() -> Self {
+        Self::from_args(std::env::args_os().skip(1))
+    }
+
+    /// Creates a layer for the given arguments, the first one is not skipped.
+    pub fn from_args<I, S>(args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<OsString>,
+    {
+        Self {
+            args: args.into_iter().map(Into::into).collect(),
+            infer_types: false,
+        }
+    }
+
+    pub fn get_args(&self) -> &[OsString] {
+        &self.args
+    }
+
+    pub fn get_infer_types(&self) -> bool {
+        self.infer_types
+    }
+
+    /// When enabled, values are parsed into booleans, `null`, numbers, and inline arrays
+    /// or tables (with the `json` or `toml` features), instead of always being strings.
+    pub fn set_infer_types(&mut self, infer_types: bool) {
+        self.infer_types = infer_types;
+    }
+
+    /// Like `set_infer_types`, but returns `self` for method chaining.
+    pub fn with_infer_types(mut self, infer_types: bool) -> Self {
+        self.set_infer_types(infer_types);
+        self
+    }
+
+    /// Parses the arguments into `(path, value)` pairs, in order.
+    fn parse_args(&self) -> Result<Vec<(ValuePath, Value)>> {
+        let args = self
+            .args
+            .iter()
+            .map(|arg| {
+                arg.clone()
+                    .into_string()
+                    .map_err(|arg| invalid_argument(&arg.to_string_lossy(), "it's not valid UTF-8"))
+            })
+            .collect::<std::result::Result<Vec<String>, _>>()?;
+
+        let mut pairs = Vec::new();
+        let mut iter = args.iter().peekable();
+
+        while let Some(arg) = iter.next() {
+            if arg == "--" {
+                break;
+            }
+
+            if arg == "-o" {
+                let option = iter
+                    .next()
+                    .ok_or_else(|| invalid_argument(arg, "expected key=value after it"))?;
+                let (key, value) = option
+                    .split_once('=')
+                    .ok_or_else(|| invalid_argument(option, "expected key=value"))?;
+                pairs.push((parse_key(option, key)?, self.parse_value(value)));
+                continue;
+            }
+
+            let flag = match arg.strip_prefix("--") {
+                Some(flag) => flag,
+                None => continue,
+            };
+
+            if let Some((key, value)) = flag.split_once('=') {
+                pairs.push((parse_key(arg, key)?, self.parse_value(value)));
+            } else if let Some(key) = flag.strip_prefix("no-") {
+                pairs.push((parse_key(arg, key)?, Value::Boolean(false)));
+            } else if let Some(value) = iter.next_if(|next| is_value(next)) {
+                pairs.push((parse_key(arg, flag)?, self.parse_value(value)));
+            } else {
+                pairs.push((parse_key(arg, flag)?, Value::Boolean(true)));
+            }
+        }
+
+        Ok(pairs)
+    }
+
+    fn parse_value(&self, value: &str) -> Value {
+        if self.infer_types {
+            infer_value(value)
+        } else {
+            Value::String(value.to_string())
+        }
+    }
+}
+
+impl Default for ArgsConfigLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns true if `arg` is the value of the flag before it, not another flag.
+fn is_value(arg: &str) -> bool {
+    !arg.starts_with('-') || arg.parse::<f64>().is_ok()
+}
+
+fn invalid_argument(arg: &str, why: &str) -> crate::BakuninError {
+    ConfigLayerError::InvalidArgument {
+        arg: arg.to_string(),
+        why: why.to_string(),
+    }
+    .into()
+}
+
+fn parse_key(arg: &str, key: &str) -> Result<ValuePath> {
+    if key.is_empty() {
+        return Err(invalid_argument(arg, "expected a key"));
+    }
+
+    ValuePath::parse(key).map_err(|e| invalid_argument(arg, &e.to_string()))
+}
+
+impl super::ConfigLayer for ArgsConfigLayer {
+    fn get_name(&self) -> &'static str {
+        "Args"
+    }
+
+    fn has_value(&self) -> bool {
+        true
+    }
+
+    fn can_write(&self) -> bool {
+        false
+    }
+
+    fn write_value(&self, _value: &Value) -> Result<()> {
+        Err(ConfigLayerError::WriteNotSupported(None).into())
+    }
+
+    fn get_source(&self, path: &ValuePath) -> Option<String> {
+        let pairs = self.parse_args().ok()?;
+        pairs
+            .iter()
+            .rev()
+            .find(|(p, _)| path.starts_with(p))
+            .map(|(p, _)| format!("--{}", p))
+    }
+
+    fn read_value(&self) -> Result<Value> {
+        let mut root = Value::new_map();
+        let mut set_paths: Vec<ValuePath> = Vec::new();
+
+        for (path, value) in self.parse_args()? {
+            if set_paths.contains(&path) {
+                // Repeated keys are collected into an array
+                if let Some(current) = root.get_path_mut(&path)? {
+                    match current {
+                        Value::Array(array) => array.push(value),
+                        current => *current = Value::Array(vec![current.clone(), value]),
+                    }
+                    continue;
+                }
+            }
+
+            // The path and the paths inside it are replaced
+            set_paths.retain(|p| !p.starts_with(&path));
+            root.set_path(&path, value)
+                .map_err(|e| invalid_argument(&format!("--{}", path), &e.to_string()))?;
+            set_paths.push(path);
+        }
+
+        Ok(root)
+    }
+}
+
+impl std::fmt::Display for ArgsConfigLayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ArgsConfigLayer[{} args]", self.args.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config_layer::ConfigLayer;
+    use crate::{value_map, value_vec};
+
+    fn read(args: &[&str]) -> Result<Value> {
+        ArgsConfigLayer::from_args(args)
+            .with_infer_types(true)
+            .read_value()
+    }
+
+    #[test]
+    fn test_read_args() {
+        let value = read(&[
+            "serve",
+            "--log.level=debug",
+            "--port",
+            "8080",
+            "-o",
+            "db.url=postgres://db",
+            "--verbose",
+            "--no-color",
+            "--host",
+            "a.com",
+            "--host=b.com",
+            "-v",
+            "input.txt",
+            "--",
+            "--ignored",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            value,
+            value_map! {
+                log: value_map! { level: "debug" },
+                port: 8080,
+                db: value_map! { url: "postgres://db" },
+                verbose: true,
+                color: false,
+                host: value_vec!["a.com", "b.com"],
+            }
+        );
+
+        let layer = ArgsConfigLayer::from_args(["--log.level=debug"]);
+        let source = layer.get_source(&ValuePath::parse("log.level").unwrap());
+        assert_eq!(source.unwrap(), "--log.level");
+    }
+
+    #[test]
+    fn test_read_overwritten_args() {
+        let value = read(&["--a.b=1", "--a=null", "--a.b=2", "--offset", "-5"]).unwrap();
+        assert_eq!(value, value_map! { a: value_map! { b: 2 }, offset: -5 });
+
+        let value = read(&["--a.b=1", "--a=x", "--a=y"]).unwrap();
+        assert_eq!(value, value_map! { a: value_vec!["x", "y"] });
+    }
+
+    #[test]
+    fn test_read_args_without_inference() {
+        let layer = ArgsConfigLayer::from_args(["--port=8080", "--version=1.10", "--debug"]);
+        assert!(!layer.get_infer_types());
+
+        let value = layer.read_value().unwrap();
+        assert_eq!(
+            value,
+            value_map! { port: "8080", version: "1.10", debug: true }
+        );
+    }
+
+    #[test]
+    fn test_read_malformed_args() {
+        for args in [
+            &["-o"][..],
+            &["-o", "key"],
+            &["--=value"],
+            &["--a..b=1"],
+            &["--a=1", "--a.b=2"],
+            &["--hosts[4000000000]=x"],
+        ] {
+            let err = read(args).unwrap_err().to_string();
+            assert!(err.contains("Invalid argument"), "{:?}: {}", args, err);
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use clap::parser::ValueSource;
+use clap::{Arg, ArgMatches, Command};
+
+use super::infer::infer_value;
+use super::ConfigLayerError;
+use crate::model::{IntoValuePath, ValuePath};
+use crate::{Result, Value};
+
+/// Id of the `--config <PATH>` argument added by `ClapConfigLayer::with_config_arg`.
+pub const CONFIG_ARG_ID: &str = "config";
+
+/// Configuration layer that reads the values of parsed `clap` arguments.
+///
+/// Only values supplied by the user, in the command line or through an environment
+/// variable, are read; clap default values are ignored so they don't override other layers.
+/// Without mappings, every argument is read into the path of its id, except the
+/// `--config` argument. With mappings, only the mapped arguments are read.
+///
+/// Values are read as the raw strings passed in the command line and parsed into
+/// numbers and booleans; arguments with more than one value are read as arrays.
+#[derive(Debug, Clone)]
+pub struct ClapConfigLayer {
+    matches: ArgMatches,
+    mappings: Vec<(String, ValuePath)>,
+}
+
+impl ClapConfigLayer {
+    pub fn new(matches: ArgMatches) -> Self {
+        Self {
+            matches,
+            mappings: Vec::new(),
+        }
+    }
+
+    pub fn get_matches(&self) -> &ArgMatches {
+        &self.matches
+    }
+
+    pub fn get_mappings(&self) -> &[(String, ValuePath)] {
+        &self.mappings
+    }
+
+    /// Reads the argument with `id` into `path`, like `log.level`, replacing any previous
+    /// mapping of `id`.
+    pub fn add_mapping<P: IntoValuePath>(&mut self, id: &str, path: P) -> Result<()> {
+        let path = path.into_value_path()?;
+        self.mappings.retain(|(i, _)| i != id);
+        self.mappings.push((id.to_string(), path));
+        Ok(())
+    }
+
+    /// Like `add_mapping`, but returns `self` for method chaining.
+    pub fn with_mapping<P: IntoValuePath>(mut self, id: &str, path: P) -> Result<Self> {
+        self.add_mapping(id, path)?;
+        Ok(self)
+    }
+
+    /// Adds the standard `--config <PATH>` argument to `command`.
+    ///
+    /// Use `BakuninConfig::add_config_arg_layer` to add the file it points to.
+    pub fn with_config_arg(command: Command) -> Command {
+        command.arg(
+            Arg::new(CONFIG_ARG_ID)
+                .long("config")
+                .value_name("PATH")
+                .help("Path to a configuration file")
+                .value_parser(clap::value_parser!(std::path::PathBuf)),
+        )
+    }
+
+    /// Returns the ids and paths of the arguments read by this layer.
+    fn arg_paths(&self) -> Result<Vec<(String, ValuePath)>> {
+        if !self.mappings.is_empty() {
+            return Ok(self.mappings.clone());
+        }
+
+        self.matches
+            .ids()
+            .map(|id| id.as_str())
+            .filter(|id| *id != CONFIG_ARG_ID)
+            .map(|id| {
+                let path = ValuePath::parse(id).map_err(|e| ConfigLayerError::InvalidArgument {
+                    arg: id.to_string(),
+                    why: e.to_string(),
+                })?;
+                Ok((id.to_string(), path))
+            })
+            .collect()
+    }
+
+    /// Returns the value of the argument with `id`, if supplied by the user.
+    fn arg_value(&self, id: &str) -> Option<Value> {
+        match self.matches.value_source(id) {
+            Some(ValueSource::CommandLine) | Some(ValueSource::EnvVariable) => (),
+            _ => return None,
+        }
+
+        // Counted flags, like `-vvv`, have no raw values
+        if let Ok(Some(count)) = self.matches.try_get_one::<u8>(id) {
+            return Some(Value::from(*count as i64));
+        }
+
+        let values: Vec<Value> = self
+            .matches
+            .try_get_raw(id)
+            .ok()??
+            .map(|raw| infer_value(&raw.to_string_lossy()))
+            .collect();
+
+        match values.len() {
+            0 => None,
+            1 => values.into_iter().next(),
+            _ => Some(Value::Array(values)),
+        }
+    }
+}
+
+impl super::ConfigLayer for ClapConfigLayer {
+    fn get_name(&self) -> &'static str {
+        "Clap"
+    }
+
+    fn has_value(&self) -> bool {
+        true
+    }
+
+    fn can_write(&self) -> bool {
+        false
+    }
+
+    fn write_value(&self, _value: &Value) -> Result<()> {
+        Err(ConfigLayerError::WriteNotSupported(None).into())
+    }
+
+    fn get_source(&self, path: &ValuePath) -> Option<String> {
+        self.arg_paths()
+            .ok()?
+            .into_iter()
+            .find(|(id, p)| path.starts_with(p) && self.arg_value(id).is_some())
+            .map(|(id, _)| format!("argument '{}'", id))
+    }
+
+    fn read_value(&self) -> Result<Value> {
+        let mut root = Value::new_map();
+
+        for (id, path) in self.arg_paths()? {
+            if let Some(value) = self.arg_value(&id) {
+                root.set_path(&path, value)?;
+            }
+        }
+
+        Ok(root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::ArgAction;
+
+    use super::*;
+    use crate::config_layer::ConfigLayer;
+    use crate::{value_map, value_vec};
+
+    fn command() -> Command {
+        let command = Command::new("app")
+            .arg(Arg::new("log.level").long("log-level"))
+            .arg(Arg::new("port").long("port").default_value("80"))
+            .arg(Arg::new("color").long("color").action(ArgAction::SetTrue))
+            .arg(Arg::new("host").long("host").action(ArgAction::Append))
+            .arg(Arg::new("verbose").short('v').action(ArgAction::Count));
+        ClapConfigLayer::with_config_arg(command)
+    }
+
+    #[test]
+    fn test_read_clap_matches() {
+        let matches = command().get_matches_from([
+            "app",
+            "--log-level",
+            "debug",
+            "--color",
+            "--host=a",
+            "--host=b",
+            "-vv",
+            "--config",
+            "app.toml",
+        ]);
+        let layer = ClapConfigLayer::new(matches);
+
+        assert_eq!(
+            layer.read_value().unwrap(),
+            value_map! {
+                log: value_map! { level: "debug" },
+                color: true,
+                host: value_vec!["a", "b"],
+                verbose: 2,
+            }
+        );
+
+        let source = layer.get_source(&ValuePath::parse("log.level").unwrap());
+        assert_eq!(source.unwrap(), "argument 'log.level'");
+        assert!(layer
+            .get_source(&ValuePath::parse("port").unwrap())
+            .is_none());
+    }
+
+    #[test]
+    fn test_read_clap_mappings() {
+        let matches = command().get_matches_from(["app", "--log-level", "debug", "--port=8080"]);
+        let layer = ClapConfigLayer::new(matches)
+            .with_mapping("port", "port")
+            .unwrap()
+            .with_mapping("port", ValuePath::parse("server.port").unwrap())
+            .unwrap();
+        assert_eq!(layer.get_mappings().len(), 1);
+
+        assert_eq!(
+            layer.read_value().unwrap(),
+            value_map! { server: value_map! { port: 8080 } }
+        );
+    }
+}
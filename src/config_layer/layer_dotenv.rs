@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::layer_file::{is_writable, lock_file, replace_file, DEFAULT_LOCK_TIMEOUT};
+use super::{dotenv, ConfigLayer, ConfigLayerError, EnvironmentConfigLayer};
+use crate::model::ValuePath;
+use crate::{Result, Value};
+
+/// Configuration layer that reads variables from a dotenv (`.env`) file.
+///
+/// Variables are read with the rules of an `EnvironmentConfigLayer`, like its prefixes,
+/// separator and mappings, as if they were set in the environment. Writing keeps the
+/// comments and the order of the file, and removes the variables of this layer that
+/// are not in the written value.
+///
+/// Writes are atomic and hold the same `<file>.lock` lock as a `FileConfigLayer`.
+#[derive(Debug, Clone)]
+pub struct DotenvConfigLayer {
+    path: PathBuf,
+    env: EnvironmentConfigLayer,
+    read_only: bool,
+}
+
+impl DotenvConfigLayer {
+    /// Creates a layer for the file at `path`, reading variables with the rules of `env`.
+    pub fn new(path: PathBuf, env: EnvironmentConfigLayer) -> Self {
+        Self {
+            path,
+            env,
+            read_only: false,
+        }
+    }
+
+    /// Creates a layer that never writes to the file.
+    pub fn read_only(path: PathBuf, env: EnvironmentConfigLayer) -> Self {
+        Self::new(path, env).with_read_only(true)
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// When enabled, writes fail with `ConfigLayerError::WriteNotSupported`.
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    /// Like `set_read_only`, but returns `self` for method chaining.
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.set_read_only(read_only);
+        self
+    }
+
+    pub fn get_path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub fn get_env_layer(&self) -> &EnvironmentConfigLayer {
+        &self.env
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    /// Returns the environment layer reading the variables of the file.
+    fn read_env(&self) -> Result<EnvironmentConfigLayer> {
+        let vars: HashMap<String, String> = dotenv::vars(&self.read_lines()?).into_iter().collect();
+        Ok(self.env.clone().with_env_source(vars))
+    }
+
+    fn read_lines(&self) -> Result<Vec<dotenv::DotenvLine>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        log::trace!("Reading dotenv file: {}", self.path.display());
+        let content = std::fs::read_to_string(&self.path)?;
+        Ok(dotenv::parse(&content)?)
+    }
+}
+
+impl ConfigLayer for DotenvConfigLayer {
+    fn get_name(&self) -> &'static str {
+        "Dotenv"
+    }
+
+    fn has_value(&self) -> bool {
+        self.path.exists()
+    }
+
+    fn can_write(&self) -> bool {
+        !self.read_only && is_writable(&self.path)
+    }
+
+    fn write_value(&self, value: &Value) -> Result<()> {
+        if !self.can_write() {
+            return Err(ConfigLayerError::WriteNotSupported(None).into());
+        }
+
+        let _lock = lock_file(&self.path, false, DEFAULT_LOCK_TIMEOUT)?;
+        let lines = self.read_lines()?;
+        let vars = self.env.export_vars(value);
+
+        let file_suffix = self.env.get_file_suffix();
+        let file_vars: Vec<String> = dotenv::vars(&lines)
+            .into_iter()
+            .map(|(key, _)| key)
+            .filter(|key| file_suffix.is_some_and(|suffix| key.ends_with(suffix)))
+            .collect();
+
+        // Values read from files are kept as references to the file
+        let vars: Vec<(String, String)> = vars
+            .into_iter()
+            .filter(|(key, _)| {
+                !file_suffix.is_some_and(|suffix| file_vars.contains(&format!("{}{}", key, suffix)))
+            })
+            .collect();
+
+        let lines = dotenv::update(lines, &vars, |key| {
+            self.env.reads_var(key) && !file_vars.iter().any(|k| k == key)
+        });
+
+        log::trace!("Writing dotenv file: {}", self.path.display());
+        replace_file(&self.path, |temp| {
+            std::fs::write(temp, dotenv::to_string(&lines))?;
+            Ok(())
+        })
+    }
+
+    fn get_source(&self, path: &ValuePath) -> Option<String> {
+        let var = self.read_env().ok()?.get_source(path)?;
+        Some(format!("{} in {}", var, self.path.display()))
+    }
+
+    fn read_value(&self) -> Result<Value> {
+        self.read_env()?.read_value()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config_layer::KeyCase;
+    use crate::{value_map, value_vec};
+
+    fn layer(name: &str, content: &str) -> DotenvConfigLayer {
+        let path = PathBuf::from(format!("./target/{}", name));
+        std::fs::write(&path, content).unwrap();
+
+        let env = EnvironmentConfigLayer::new("APP_")
+            .with_separator("__")
+            .with_key_case(KeyCase::Lower);
+        DotenvConfigLayer::new(path, env)
+    }
+
+    #[test]
+    fn test_read_dotenv() {
+        let layer = layer(
+            "test_read.env",
+            "# local overrides\nexport APP_LOG__LEVEL=debug\nAPP_HOSTS__0=a\nAPP_HOSTS__1=b\nOTHER=x\n",
+        );
+
+        let value = layer.read_value().unwrap();
+
+        assert_eq!(value.get("log").get("level"), Value::from("debug"));
+        assert_eq!(value.get("hosts"), value_vec!["a", "b"]);
+        assert!(!value.contains_path("other"));
+
+        let source = layer.get_source(&ValuePath::parse("log.level").unwrap());
+        assert_eq!(source.unwrap(), "APP_LOG__LEVEL in ./target/test_read.env");
+    }
+
+    #[test]
+    fn test_read_dotenv_ignores_far_indexes() {
+        let layer = layer(
+            "test_read_far_index.env",
+            "APP_HOSTS__0=a\nAPP_HOSTS__4000000000=x\n",
+        );
+
+        assert_eq!(
+            layer.read_value().unwrap(),
+            value_map! { hosts: value_vec!["a"] }
+        );
+    }
+
+    #[test]
+    fn test_write_dotenv_keeps_comments() {
+        let layer = layer(
+            "test_write.env",
+            "# local overrides\nAPP_LOG__LEVEL=debug # verbose\nAPP_NAME=app\nOTHER=x\n",
+        );
+
+        let value = value_map! {
+            log: value_map! { level: "debug" },
+            hosts: value_vec!["a", "b c"],
+        };
+        layer.write_value(&value).unwrap();
+
+        let content = std::fs::read_to_string(layer.get_path()).unwrap();
+        assert_eq!(
+            content,
+            "# local overrides\nAPP_LOG__LEVEL=debug # verbose\nOTHER=x\nAPP_HOSTS__0=a\nAPP_HOSTS__1=\"b c\"\n"
+        );
+        assert_eq!(layer.read_value().unwrap(), value);
+    }
+}
@@ -1,23 +1,541 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
-use super::ConfigLayerError;
+use super::infer::infer_value;
+use super::{dotenv, ConfigLayerError, EnvSource, ProcessEnv};
+use crate::model::{IntoValuePath, PathSegment, ValuePath, ValueType};
 use crate::{Result, Value};
 
+/// Case conversion applied to keys read from environment variables.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeyCase {
+    /// Keys are used as written in the variable name.
+    #[default]
+    Preserve,
+    /// Keys are converted to lower case, e.g. `MY_APP_LOG__LEVEL` sets `log.level`.
+    Lower,
+    /// Keys are converted to upper case.
+    Upper,
+}
+
+impl KeyCase {
+    pub fn apply(&self, key: &str) -> String {
+        match self {
+            KeyCase::Preserve => key.to_string(),
+            KeyCase::Lower => key.to_lowercase(),
+            KeyCase::Upper => key.to_uppercase(),
+        }
+    }
+}
+
+/// Maps a variable with an exact name, like `DATABASE_URL`, to a key path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvMapping {
+    pub var: String,
+    pub path: ValuePath,
+    /// Type the value is converted to, it's kept as a string if `None`.
+    pub value_type: Option<ValueType>,
+}
+
+impl EnvMapping {
+    /// Converts the variable value to the mapping type.
+    fn convert(&self, value: &str) -> std::result::Result<Value, String> {
+        let value_type = match self.value_type {
+            None | Some(ValueType::String) => return Ok(Value::String(value.to_string())),
+            Some(ValueType::Bytes) => {
+                return Value::from(value)
+                    .try_into_bytes()
+                    .map(Value::Bytes)
+                    .map_err(|e| e.to_string())
+            }
+            Some(value_type) => value_type,
+        };
+
+        match (value_type, infer_value(value)) {
+            (ValueType::Float, Value::Integer(v)) => Ok(Value::Float(v as f64)),
+            (value_type, v) if value_type.matches(&v) => Ok(v),
+            _ => Err(format!("'{}' is not a valid {}", value, value_type)),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct EnvironmentConfigLayer {
-    prefix: &'static str,
+    prefixes: Vec<&'static str>,
+    mappings: Vec<EnvMapping>,
+    separator: Option<&'static str>,
+    key_case: KeyCase,
+    delimited_keys: Vec<(ValuePath, &'static str)>,
+    infer_types: bool,
+    file_suffix: Option<&'static str>,
+    source: Arc<dyn EnvSource + Send + Sync>,
 }
 
 /// Configuration layer that reads values from environment variables with a specified prefix.
+///
+/// More prefixes can be added with `with_prefix`, later ones take precedence when two
+/// variables set the same key. Variables with conventional names, like `PORT`, can be
+/// mapped to keys with `with_mapping`, and take precedence over prefixed variables.
+///
+/// By default each variable sets a top-level key. With a separator, e.g. `__`, the variable
+/// name is split into nested keys, so `MY_APP_LOG__LEVEL` sets `LOG.LEVEL`, or `log.level`
+/// with `KeyCase::Lower`.
+///
+/// Lists can be set with indexed variables, like `MY_APP_HOSTS__0` and `MY_APP_HOSTS__1`,
+/// when a separator is set, or by splitting the value of keys marked with `with_delimited_key`.
 impl EnvironmentConfigLayer {
     pub fn new(prefix: &'static str) -> Self {
+        Self::unprefixed().with_prefix(prefix)
+    }
+
+    /// Creates a layer without prefixes, that only reads mapped variables.
+    pub fn unprefixed() -> Self {
         Self {
-            prefix: prefix.trim(),
+            prefixes: Vec::new(),
+            mappings: Vec::new(),
+            separator: None,
+            key_case: KeyCase::default(),
+            delimited_keys: Vec::new(),
+            infer_types: false,
+            file_suffix: None,
+            source: Arc::new(ProcessEnv),
         }
     }
 
+    /// Returns the first prefix, or an empty string if there is none.
     pub fn get_prefix(&self) -> &'static str {
-        self.prefix
+        self.prefixes.first().copied().unwrap_or_default()
+    }
+
+    pub fn get_prefixes(&self) -> &[&'static str] {
+        &self.prefixes
+    }
+
+    /// Adds a prefix of the variables read by this layer, empty prefixes are ignored.
+    pub fn add_prefix(&mut self, prefix: &'static str) {
+        let prefix = prefix.trim();
+        if !prefix.is_empty() && !self.prefixes.contains(&prefix) {
+            self.prefixes.push(prefix);
+        }
+    }
+
+    /// Like `add_prefix`, but returns `self` for method chaining.
+    pub fn with_prefix(mut self, prefix: &'static str) -> Self {
+        self.add_prefix(prefix);
+        self
+    }
+
+    pub fn get_mappings(&self) -> &[EnvMapping] {
+        &self.mappings
+    }
+
+    /// Maps the variable `var` to `path`, converting its value to `value_type` if set.
+    ///
+    /// A variable can only be mapped to one path, mapping it again replaces the previous one.
+    pub fn add_mapping<P: IntoValuePath>(
+        &mut self,
+        var: &str,
+        path: P,
+        value_type: Option<ValueType>,
+    ) -> Result<()> {
+        let path = path.into_value_path()?;
+        self.mappings.retain(|m| m.var != var);
+        self.mappings.push(EnvMapping {
+            var: var.to_string(),
+            path,
+            value_type,
+        });
+        Ok(())
+    }
+
+    /// Like `add_mapping`, without type conversion, but returns `self` for method chaining.
+    pub fn with_mapping<P: IntoValuePath>(mut self, var: &str, path: P) -> Result<Self> {
+        self.add_mapping(var, path, None)?;
+        Ok(self)
+    }
+
+    /// Like `add_mapping`, but returns `self` for method chaining.
+    pub fn with_typed_mapping<P: IntoValuePath>(
+        mut self,
+        var: &str,
+        path: P,
+        value_type: ValueType,
+    ) -> Result<Self> {
+        self.add_mapping(var, path, Some(value_type))?;
+        Ok(self)
+    }
+
+    pub fn get_separator(&self) -> Option<&'static str> {
+        self.separator
+    }
+
+    /// Sets the separator used to split variable names into nested keys, `None` disables nesting.
+    pub fn set_separator(&mut self, separator: Option<&'static str>) {
+        self.separator = separator.filter(|s| !s.is_empty());
+    }
+
+    /// Like `set_separator`, but returns `self` for method chaining.
+    pub fn with_separator(mut self, separator: &'static str) -> Self {
+        self.set_separator(Some(separator));
+        self
+    }
+
+    pub fn get_key_case(&self) -> KeyCase {
+        self.key_case
+    }
+
+    /// Sets the case conversion applied to keys, the prefix is always matched as written.
+    pub fn set_key_case(&mut self, key_case: KeyCase) {
+        self.key_case = key_case;
+    }
+
+    /// Like `set_key_case`, but returns `self` for method chaining.
+    pub fn with_key_case(mut self, key_case: KeyCase) -> Self {
+        self.set_key_case(key_case);
+        self
+    }
+
+    pub fn get_env_source(&self) -> &dyn EnvSource {
+        self.source.as_ref()
+    }
+
+    /// Sets where variables are read from, by default the process environment.
+    pub fn set_env_source<S: EnvSource + Send + Sync + 'static>(&mut self, source: S) {
+        self.source = Arc::new(source);
+    }
+
+    /// Like `set_env_source`, but returns `self` for method chaining.
+    pub fn with_env_source<S: EnvSource + Send + Sync + 'static>(mut self, source: S) -> Self {
+        self.set_env_source(source);
+        self
+    }
+
+    pub fn get_file_suffix(&self) -> Option<&'static str> {
+        self.file_suffix
+    }
+
+    /// Sets the suffix of variables that hold the path of a file with the value, like
+    /// `MY_APP_DB_PASSWORD_FILE=/run/secrets/db` for `DB_PASSWORD`. `None` disables it.
+    ///
+    /// The file content is used without its trailing newline. Setting both the variable
+    /// and the one with the suffix is an error.
+    pub fn set_file_suffix(&mut self, suffix: Option<&'static str>) {
+        self.file_suffix = suffix.filter(|s| !s.is_empty());
+    }
+
+    /// Like `set_file_suffix`, but returns `self` for method chaining.
+    pub fn with_file_suffix(mut self, suffix: &'static str) -> Self {
+        self.set_file_suffix(Some(suffix));
+        self
+    }
+
+    pub fn get_infer_types(&self) -> bool {
+        self.infer_types
+    }
+
+    /// When enabled, values are parsed into booleans, `null`, numbers, and inline
+    /// arrays or tables (with the `json` or `toml` features), instead of always being strings.
+    ///
+    /// Numbers with leading zeros, like `007`, are kept as strings.
+    pub fn set_infer_types(&mut self, infer_types: bool) {
+        self.infer_types = infer_types;
+    }
+
+    /// Like `set_infer_types`, but returns `self` for method chaining.
+    pub fn with_infer_types(mut self, infer_types: bool) -> Self {
+        self.set_infer_types(infer_types);
+        self
+    }
+
+    /// Returns the delimiter used to split the value at `path`, if any.
+    pub fn get_delimiter(&self, path: &ValuePath) -> Option<&'static str> {
+        self.delimited_keys
+            .iter()
+            .find(|(p, _)| p == path)
+            .map(|(_, delimiter)| *delimiter)
+    }
+
+    /// Splits the value of the variable that sets `path` by `delimiter`, creating an array.
+    ///
+    /// The path must match the keys after the separator and case conversion,
+    /// e.g. `allowed_hosts` with `KeyCase::Lower`. Elements are trimmed.
+    pub fn set_delimited_key<P: IntoValuePath>(
+        &mut self,
+        path: P,
+        delimiter: &'static str,
+    ) -> Result<()> {
+        let path = path.into_value_path()?;
+        self.delimited_keys.retain(|(p, _)| p != &path);
+        self.delimited_keys.push((path, delimiter));
+        Ok(())
+    }
+
+    /// Like `set_delimited_key`, but returns `self` for method chaining.
+    pub fn with_delimited_key<P: IntoValuePath>(
+        mut self,
+        path: P,
+        delimiter: &'static str,
+    ) -> Result<Self> {
+        self.set_delimited_key(path, delimiter)?;
+        Ok(self)
+    }
+
+    /// Converts a variable value to the value set at `path`.
+    fn parse_value(&self, path: &ValuePath, value: String) -> Value {
+        match self.get_delimiter(path) {
+            Some(_) if value.trim().is_empty() => Value::new_array(),
+            Some(delimiter) => Value::Array(
+                value
+                    .split(delimiter)
+                    .map(|v| self.parse_scalar(v.trim()))
+                    .collect(),
+            ),
+            None => self.parse_scalar(&value),
+        }
+    }
+
+    fn parse_scalar(&self, value: &str) -> Value {
+        if self.infer_types {
+            infer_value(value)
+        } else {
+            Value::String(value.to_string())
+        }
+    }
+
+    /// Returns the variables from the source with valid UTF-8 names and values.
+    fn read_vars(&self) -> Result<Vec<(String, String)>> {
+        let mut vars = Vec::new();
+
+        for (key, value) in self.source.vars() {
+            let key = match key.into_string() {
+                Ok(key) => key.trim().to_string(),
+                Err(key) => {
+                    log::trace!("Invalid environment variable key: {:?}", key);
+                    continue;
+                }
+            };
+
+            match value.into_string() {
+                Ok(value) => vars.push((key, value)),
+                Err(value) => {
+                    log::debug!(
+                        "Invalid environment variable [{:?}] value: {:?}",
+                        key,
+                        value
+                    );
+                }
+            }
+        }
+
+        match self.file_suffix {
+            Some(suffix) => self.resolve_files(vars, suffix),
+            None => Ok(vars),
+        }
+    }
+
+    /// Replaces variables ending with `suffix` by the content of the file they point to.
+    ///
+    /// Only variables read by this layer are resolved.
+    fn resolve_files(
+        &self,
+        vars: Vec<(String, String)>,
+        suffix: &str,
+    ) -> Result<Vec<(String, String)>> {
+        let (files, mut vars): (Vec<_>, Vec<_>) = vars.into_iter().partition(|(name, _)| {
+            name.strip_suffix(suffix)
+                .is_some_and(|name| self.reads_var(name))
+        });
+
+        for (file_var, path) in files {
+            let name = &file_var[..file_var.len() - suffix.len()];
+
+            if vars.iter().any(|(n, _)| n == name) {
+                return Err(ConfigLayerError::ErrorReadingValue(format!(
+                    "Both {} and {} are set, only one can be used",
+                    name, file_var
+                ))
+                .into());
+            }
+
+            log::trace!("Reading {} from file {}", file_var, path);
+            let content =
+                std::fs::read_to_string(&path).map_err(|e| ConfigLayerError::ErrorReadingFile {
+                    var: file_var.clone(),
+                    path: path.clone().into(),
+                    source: e,
+                })?;
+
+            let content = content
+                .strip_suffix('\n')
+                .map(|c| c.strip_suffix('\r').unwrap_or(c))
+                .unwrap_or(&content);
+            vars.push((name.to_string(), content.to_string()));
+        }
+
+        Ok(vars)
+    }
+
+    /// Returns true if the variable `name` matches a prefix or a mapping of this layer.
+    pub(crate) fn reads_var(&self, name: &str) -> bool {
+        self.prefixes.iter().any(|prefix| name.starts_with(prefix))
+            || self.mappings.iter().any(|m| m.var == name)
+    }
+
+    /// Converts `value` to the variables that set it when read by this layer, sorted by name.
+    ///
+    /// Variables are named with the first prefix, or the mapped name for mapped paths. Arrays
+    /// are written as indexed variables, or joined for delimited keys. Reading the variables
+    /// back gives the same value, as strings unless `with_infer_types` is enabled.
+    ///
+    /// `Value::None`, empty arrays and maps are skipped, as are values that can't be set by
+    /// a variable, like nested values without a separator, with a warning.
+    pub fn export_vars(&self, value: &Value) -> Vec<(String, String)> {
+        let mut vars = Vec::new();
+        self.collect_vars(value, &mut ValuePath::root(), &mut vars);
+        vars.sort();
+        vars
+    }
+
+    /// Like `export_vars`, but formats the variables as the content of a dotenv (`.env`) file.
+    pub fn export_dotenv(&self, value: &Value) -> String {
+        self.export_vars(value)
+            .iter()
+            .map(|(key, value)| format!("{}\n", dotenv::format_var(key, value)))
+            .collect()
+    }
+
+    /// Like `export_vars`, but sets the variables in the environment of `command`.
+    pub fn export_to_command<'a>(
+        &self,
+        value: &Value,
+        command: &'a mut std::process::Command,
+    ) -> &'a mut std::process::Command {
+        command.envs(self.export_vars(value))
+    }
+
+    fn collect_vars(&self, value: &Value, path: &mut ValuePath, vars: &mut Vec<(String, String)>) {
+        if let Some(mapping) = self.mappings.iter().find(|m| &m.path == path) {
+            match value {
+                Value::Map(_) | Value::Array(_) => {
+                    log::warn!("Can't set {} with {}: not a scalar", path, mapping.var)
+                }
+                Value::None => {}
+                value => vars.push((mapping.var.clone(), value.to_string())),
+            }
+            return;
+        }
+
+        let nested = path.is_root() || self.separator.is_some();
+        match value {
+            Value::None => {}
+            Value::Map(map) if nested => {
+                let mut keys: Vec<&String> = map.keys().collect();
+                keys.sort();
+                for key in keys {
+                    path.push_key(key.as_str());
+                    self.collect_vars(&map[key], path, vars);
+                    path.pop();
+                }
+            }
+            Value::Array(array) if !path.is_root() => match self.get_delimiter(path) {
+                Some(delimiter) => {
+                    let items: Vec<String> = array.iter().map(|v| v.to_string()).collect();
+                    self.push_var(path, items.join(delimiter), vars);
+                }
+                None if self.separator.is_some() => {
+                    for (index, item) in array.iter().enumerate() {
+                        path.push_index(index);
+                        self.collect_vars(item, path, vars);
+                        path.pop();
+                    }
+                }
+                None => log::warn!("Can't set {} without a separator or delimiter", path),
+            },
+            Value::Map(_) | Value::Array(_) => {
+                log::warn!("Can't set {} without a separator", path)
+            }
+            value if path.is_root() => log::warn!("Can't set {} as the root value", value),
+            value => self.push_var(path, value.to_string(), vars),
+        }
+    }
+
+    fn push_var(&self, path: &ValuePath, value: String, vars: &mut Vec<(String, String)>) {
+        match (self.prefixes.first(), self.path_to_key(path)) {
+            (Some(prefix), Some(key)) => vars.push((format!("{}{}", prefix, key), value)),
+            _ => log::warn!("Can't set {}: no variable name for it", path),
+        }
+    }
+
+    /// Converts a variable name, without the prefix, to the path it sets.
+    ///
+    /// Numeric keys after the first one are array indexes.
+    fn key_to_path(&self, key: &str) -> Option<ValuePath> {
+        let keys: Vec<&str> = match self.separator {
+            Some(separator) => key.split(separator).collect(),
+            None => vec![key],
+        };
+
+        if keys.iter().any(|k| k.is_empty()) {
+            return None;
+        }
+
+        let segments = keys
+            .into_iter()
+            .enumerate()
+            .map(|(i, k)| match k.parse::<usize>() {
+                Ok(index) if i > 0 && k.bytes().all(|b| b.is_ascii_digit()) => {
+                    PathSegment::Index(index)
+                }
+                _ => PathSegment::Key(self.key_case.apply(k)),
+            })
+            .collect::<Vec<_>>();
+
+        Some(ValuePath::from(segments))
+    }
+
+    /// Converts a path back to the name of the variable that sets it.
+    fn path_to_key(&self, path: &ValuePath) -> Option<String> {
+        let separator = match self.separator {
+            Some(separator) => separator,
+            None => {
+                return match path.segments().first() {
+                    Some(PathSegment::Key(key)) => Some(self.key_to_name(key)),
+                    _ => None,
+                }
+            }
+        };
+
+        let mut keys = Vec::new();
+        let mut current = ValuePath::root();
+        for segment in path.segments() {
+            if self.get_delimiter(&current).is_some() {
+                break;
+            }
+
+            match segment {
+                PathSegment::Key(key) => {
+                    keys.push(self.key_to_name(key));
+                    current.push_key(key);
+                }
+                PathSegment::Index(index) => {
+                    keys.push(index.to_string());
+                    current.push_index(*index);
+                }
+            }
+        }
+
+        if keys.is_empty() {
+            return None;
+        }
+
+        Some(keys.join(separator))
+    }
+
+    fn key_to_name(&self, key: &str) -> String {
+        match self.key_case {
+            KeyCase::Lower => key.to_uppercase(),
+            _ => key.to_string(),
+        }
     }
 }
 
@@ -35,11 +553,49 @@ impl super::ConfigLayer for EnvironmentConfigLayer {
     }
 
     fn write_value(&self, _value: &Value) -> Result<()> {
-        Err(ConfigLayerError::WriteNotSupported.into())
+        Err(ConfigLayerError::WriteNotSupported(None).into())
+    }
+
+    fn get_source(&self, path: &ValuePath) -> Option<String> {
+        let names: Vec<String> = self
+            .source
+            .vars()
+            .into_iter()
+            .filter_map(|(k, _)| k.into_string().ok())
+            .collect();
+
+        // Name of the variable that was read, either `name` or the one with the file suffix
+        let find = |name: String| -> Option<String> {
+            if names.contains(&name) {
+                return Some(name);
+            }
+            let file_name = format!("{}{}", name, self.file_suffix?);
+            names.contains(&file_name).then_some(file_name)
+        };
+
+        let mapped = self
+            .mappings
+            .iter()
+            .filter(|m| path.starts_with(&m.path))
+            .find_map(|m| find(m.var.clone()));
+        if mapped.is_some() {
+            return mapped;
+        }
+
+        let key = self.path_to_key(path)?;
+        self.prefixes
+            .iter()
+            .rev()
+            .find_map(|prefix| find(format!("{}{}", prefix, key)))
+            .or_else(|| {
+                self.prefixes
+                    .first()
+                    .map(|prefix| format!("{}{}", prefix, key))
+            })
     }
 
     fn read_value(&self) -> Result<Value> {
-        if self.prefix.is_empty() {
+        if self.prefixes.is_empty() && self.mappings.is_empty() {
             return Err(ConfigLayerError::ErrorReadingValue(
                 "Environment prefix cannot be empty".to_string(),
             )
@@ -47,65 +603,133 @@ impl super::ConfigLayer for EnvironmentConfigLayer {
         }
 
         log::trace!(
-            "Loading environment variables with prefix {:?}",
-            self.prefix
+            "Loading environment variables with prefixes {:?}",
+            self.prefixes
         );
-        let mut map = HashMap::new();
+        let vars = self.read_vars()?;
 
-        for (key, value) in std::env::vars_os() {
-            let str_key = match key.into_string() {
-                Ok(str_key) => str_key.trim().to_string(),
-                Err(key) => {
-                    log::trace!("Invalid environment variable key: {:?}", key);
+        let mut prefixed = Vec::new();
+        for (name, value) in vars.iter() {
+            // Longest matching prefix, so `APP_DB_` is used over `APP_` for `APP_DB_URL`
+            let matched = self
+                .prefixes
+                .iter()
+                .enumerate()
+                .filter(|(_, prefix)| name.starts_with(**prefix))
+                .max_by_key(|(_, prefix)| prefix.len());
+
+            if let Some((index, prefix)) = matched {
+                prefixed.push((&name[prefix.len()..], index, name, value));
+            }
+        }
+
+        // Sorted, so conflicts like `A` and `A__B` are always resolved the same way,
+        // and later prefixes override earlier ones
+        prefixed.sort();
+
+        let mut root = Value::Map(HashMap::new());
+        let mut set_paths = Vec::new();
+        for (key, _, name, value) in prefixed {
+            let path = match self.key_to_path(key) {
+                Some(path) => path,
+                None => {
+                    log::warn!("Ignoring environment variable {}: empty key", name);
                     continue;
                 }
             };
 
-            if !str_key.starts_with(self.prefix) {
-                continue;
+            let value = self.parse_value(&path, value.clone());
+            match root.set_path(&path, value) {
+                Ok(_) => set_paths.push(path),
+                Err(e) => log::warn!("Ignoring environment variable {}: {}", name, e),
             }
+        }
 
-            let key = str_key.trim_start_matches(self.prefix);
+        for mapping in self.mappings.iter() {
+            let value = match vars.iter().find(|(name, _)| name == &mapping.var) {
+                Some((_, value)) => value,
+                None => continue,
+            };
 
-            let value = match value.into_string() {
+            let value = match mapping.convert(value) {
                 Ok(value) => value,
-                Err(value) => {
-                    log::debug!(
-                        "Invalid environment variable [{:?}] value: {:?}",
-                        key,
-                        value
-                    );
+                Err(e) => {
+                    log::warn!("Ignoring environment variable {}: {}", mapping.var, e);
                     continue;
                 }
             };
 
-            map.insert(key.to_string(), Value::String(value));
+            match root.set_path(&mapping.path, value) {
+                Ok(_) => set_paths.push(mapping.path.clone()),
+                Err(e) => log::warn!("Ignoring environment variable {}: {}", mapping.var, e),
+            }
         }
 
-        Ok(Value::Map(map))
+        remove_padding(&mut root, &mut ValuePath::root(), &set_paths);
+        Ok(root)
+    }
+}
+
+/// Removes the `Value::None` elements added to arrays when indexes are missing,
+/// keeping the ones inside values explicitly set in `set_paths`.
+fn remove_padding(value: &mut Value, path: &mut ValuePath, set_paths: &[ValuePath]) {
+    match value {
+        Value::Array(array) => {
+            let mut index = 0;
+            array.retain(|v| {
+                path.push_index(index);
+                let keep = !v.is_none() || set_paths.iter().any(|p| path.starts_with(p));
+                path.pop();
+                index += 1;
+                keep
+            });
+
+            for (index, value) in array.iter_mut().enumerate() {
+                path.push_index(index);
+                remove_padding(value, path, set_paths);
+                path.pop();
+            }
+        }
+        Value::Map(map) => {
+            for (key, value) in map.iter_mut() {
+                path.push_key(key.as_str());
+                remove_padding(value, path, set_paths);
+                path.pop();
+            }
+        }
+        _ => {}
     }
 }
 
 impl std::fmt::Display for EnvironmentConfigLayer {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "EnvironmentConfigLayer[{}]", self.prefix)
+        write!(f, "EnvironmentConfigLayer[{}]", self.prefixes.join(", "))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::config_layer::ConfigLayer;
+    use crate::{value_map, value_vec};
 
     use super::*;
 
+    fn env(vars: &[(&str, &str)]) -> HashMap<String, String> {
+        vars.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
     #[test]
     fn test_load_environment() {
-        std::env::set_var("TEST_value1", "1");
-        std::env::set_var("TEST_value2", "2.5");
-        std::env::set_var("TEST_value3", "true");
-        std::env::set_var("TEST_value4", "data");
+        let env = env(&[
+            ("TEST_value1", "1"),
+            ("TEST_value2", "2.5"),
+            ("TEST_value3", "true"),
+            ("TEST_value4", "data"),
+        ]);
 
-        let layer = EnvironmentConfigLayer::new("TEST_");
+        let layer = EnvironmentConfigLayer::new("TEST_").with_env_source(env);
         let value = layer.read_value().unwrap();
 
         assert_eq!(value.get("value1").try_into_i64().unwrap(), 1);
@@ -113,4 +737,302 @@ mod tests {
         assert_eq!(value.get("value3").try_into_bool().unwrap(), true);
         assert_eq!(value.get("value4").try_into_string().unwrap(), "data");
     }
+
+    #[test]
+    fn test_load_nested_environment() {
+        let env = env(&[
+            ("TEST_NESTED_LOG__LEVEL", "debug"),
+            ("TEST_NESTED_LOG__FILE__PATH", "/tmp/app.log"),
+            ("TEST_NESTED_PORT", "80"),
+            ("TEST_NESTED_PORT__NUMBER", "81"),
+            ("TEST_NESTED_BAD__", "1"),
+        ]);
+
+        let layer = EnvironmentConfigLayer::new("TEST_NESTED_")
+            .with_env_source(env)
+            .with_separator("__")
+            .with_key_case(KeyCase::Lower);
+        let value = layer.read_value().unwrap();
+
+        assert_eq!(
+            value.get("log").get("level").try_into_string().unwrap(),
+            "debug"
+        );
+        assert_eq!(
+            value.get_path("log.file.path").unwrap().unwrap(),
+            &Value::from("/tmp/app.log")
+        );
+        assert_eq!(value.get("port").try_into_i64().unwrap(), 80);
+        assert!(!value.contains_path("bad"));
+
+        let source = layer.get_source(&ValuePath::parse("log.file.path").unwrap());
+        assert_eq!(source.unwrap(), "TEST_NESTED_LOG__FILE__PATH");
+    }
+
+    #[test]
+    fn test_nested_environment_overrides_lower_layer() {
+        let env = env(&[("TEST_MERGE_SERVER__PORT", "9000")]);
+
+        let config = crate::BakuninConfig::new()
+            .with_memory_layer(
+                "default",
+                crate::value_map! {
+                    server: crate::value_map! { host: "localhost", port: 80 }
+                },
+            )
+            .with_layer(
+                "env",
+                Box::new(
+                    EnvironmentConfigLayer::new("TEST_MERGE_")
+                        .with_env_source(env)
+                        .with_separator("__")
+                        .with_key_case(KeyCase::Lower),
+                ),
+            );
+
+        let value = config.build_value(false).unwrap();
+
+        assert_eq!(
+            value.get("server").get("host").try_into_string().unwrap(),
+            "localhost"
+        );
+        assert_eq!(
+            value.get("server").get("port").try_into_i64().unwrap(),
+            9000
+        );
+    }
+
+    #[test]
+    fn test_load_environment_arrays() {
+        let env = env(&[
+            ("TEST_ARRAY_HOSTS__0", "a.com"),
+            ("TEST_ARRAY_HOSTS__1", "b.com"),
+            ("TEST_ARRAY_HOSTS__3", "c.com"),
+            ("TEST_ARRAY_USERS__0__NAME", "root"),
+            ("TEST_ARRAY_ALLOWED_ORIGINS", "x.com, y.com,z.com"),
+            ("TEST_ARRAY_EMPTY", ""),
+        ]);
+
+        let layer = EnvironmentConfigLayer::new("TEST_ARRAY_")
+            .with_env_source(env)
+            .with_separator("__")
+            .with_key_case(KeyCase::Lower)
+            .with_delimited_key("allowed_origins", ",")
+            .unwrap()
+            .with_delimited_key("empty", ",")
+            .unwrap();
+        let value = layer.read_value().unwrap();
+
+        assert_eq!(value.get("hosts"), value_vec!["a.com", "b.com", "c.com"]);
+        assert_eq!(value.get("users"), value_vec![value_map! { name: "root" }]);
+        assert_eq!(
+            value.get("allowed_origins"),
+            value_vec!["x.com", "y.com", "z.com"]
+        );
+        assert_eq!(value.get("empty"), Value::new_array());
+
+        let source = layer.get_source(&ValuePath::parse("users[0].name").unwrap());
+        assert_eq!(source.unwrap(), "TEST_ARRAY_USERS__0__NAME");
+        let source = layer.get_source(&ValuePath::parse("allowed_origins[1]").unwrap());
+        assert_eq!(source.unwrap(), "TEST_ARRAY_ALLOWED_ORIGINS");
+    }
+
+    #[test]
+    fn test_load_environment_ignores_far_indexes() {
+        let env = env(&[
+            ("APP_HOSTS__0", "a.com"),
+            ("APP_HOSTS__4000000000", "b.com"),
+            ("APP_HOSTS__18446744073709551615", "c.com"),
+            ("APP_PORT", "80"),
+        ]);
+
+        let layer = EnvironmentConfigLayer::new("APP_")
+            .with_env_source(env)
+            .with_separator("__")
+            .with_key_case(KeyCase::Lower);
+
+        assert_eq!(
+            layer.read_value().unwrap(),
+            value_map! { hosts: value_vec!["a.com"], port: "80" }
+        );
+    }
+
+    #[test]
+    fn test_load_environment_infer_types() {
+        let env = env(&[
+            ("TEST_INFER_PORT", "8080"),
+            ("TEST_INFER_RATIO", "0.75"),
+            ("TEST_INFER_DEBUG", "true"),
+            ("TEST_INFER_CODE", "007"),
+            ("TEST_INFER_PARENT", "null"),
+            ("TEST_INFER_LIST__0", "1"),
+            ("TEST_INFER_LIST__2", "null"),
+            ("TEST_INFER_PORTS", "80, 443"),
+        ]);
+
+        let layer = EnvironmentConfigLayer::new("TEST_INFER_")
+            .with_env_source(env)
+            .with_separator("__")
+            .with_key_case(KeyCase::Lower)
+            .with_delimited_key("ports", ",")
+            .unwrap()
+            .with_infer_types(true);
+        let value = layer.read_value().unwrap();
+
+        assert_eq!(value.get("port"), Value::Integer(8080));
+        assert_eq!(value.get("ratio"), Value::Float(0.75));
+        assert_eq!(value.get("debug"), Value::Boolean(true));
+        assert_eq!(value.get("code"), Value::from("007"));
+        assert!(value.contains_path("parent"));
+        assert_eq!(value.get("parent"), Value::None);
+        assert_eq!(value.get("list"), value_vec![1, Value::None]);
+        assert_eq!(value.get("ports"), value_vec![80, 443]);
+
+        let config = crate::BakuninConfig::new()
+            .with_memory_layer("default", value_map! { port: 80 })
+            .with_layer("env", Box::new(layer));
+        let value = config.build_value(false).unwrap();
+        assert_eq!(value.get("port"), Value::Integer(8080));
+    }
+
+    #[test]
+    fn test_load_environment_mappings() {
+        let env = env(&[
+            ("DATABASE_URL", "postgres://db"),
+            ("PORT", "8080"),
+            ("DEBUG", "maybe"),
+            ("APP_name", "app"),
+            ("APP_level", "info"),
+            ("SVC_level", "debug"),
+            ("SVC_port", "1"),
+        ]);
+
+        let layer = EnvironmentConfigLayer::new("APP_")
+            .with_prefix("SVC_")
+            .with_env_source(env)
+            .with_mapping("DATABASE_URL", "database.url")
+            .unwrap()
+            .with_typed_mapping("PORT", "port", ValueType::Integer)
+            .unwrap()
+            .with_typed_mapping("DEBUG", "debug", ValueType::Boolean)
+            .unwrap();
+        let value = layer.read_value().unwrap();
+
+        assert_eq!(
+            value.get("database").get("url"),
+            Value::from("postgres://db")
+        );
+        assert_eq!(value.get("port"), Value::Integer(8080));
+        assert!(!value.contains_path("debug"));
+        assert_eq!(value.get("name"), Value::from("app"));
+        assert_eq!(value.get("level"), Value::from("debug"));
+
+        let source = layer.get_source(&ValuePath::parse("database.url").unwrap());
+        assert_eq!(source.unwrap(), "DATABASE_URL");
+        let source = layer.get_source(&ValuePath::parse("level").unwrap());
+        assert_eq!(source.unwrap(), "SVC_level");
+        let source = layer.get_source(&ValuePath::parse("name").unwrap());
+        assert_eq!(source.unwrap(), "APP_name");
+    }
+
+    #[test]
+    fn test_load_environment_file_indirection() {
+        let path = "./target/test_env_secret.txt";
+        std::fs::write(path, "s3cret\n").unwrap();
+
+        let env = env(&[
+            ("TEST_FILE_DB__PASSWORD_FILE", path),
+            ("TEST_FILE_NAME", "app"),
+            ("OTHER_FILE", "/does/not/exist"),
+        ]);
+
+        let layer = EnvironmentConfigLayer::new("TEST_FILE_")
+            .with_separator("__")
+            .with_key_case(KeyCase::Lower)
+            .with_file_suffix("_FILE")
+            .with_env_source(env);
+        let value = layer.read_value().unwrap();
+
+        assert_eq!(value.get("db").get("password"), Value::from("s3cret"));
+        assert_eq!(value.get("name"), Value::from("app"));
+
+        let source = layer.get_source(&ValuePath::parse("db.password").unwrap());
+        assert_eq!(source.unwrap(), "TEST_FILE_DB__PASSWORD_FILE");
+    }
+
+    #[test]
+    fn test_load_environment_file_indirection_errors() {
+        let missing = env(&[("TEST_FILE_KEY_FILE", "./target/does-not-exist.txt")]);
+        let layer = EnvironmentConfigLayer::new("TEST_FILE_")
+            .with_file_suffix("_FILE")
+            .with_env_source(missing);
+
+        let err = layer.read_value().unwrap_err().to_string();
+        assert!(err.contains("TEST_FILE_KEY_FILE"), "{}", err);
+        assert!(err.contains("does-not-exist.txt"), "{}", err);
+
+        let both = env(&[
+            ("TEST_FILE_KEY_FILE", "./target/does-not-exist.txt"),
+            ("TEST_FILE_KEY", "value"),
+        ]);
+        let layer = EnvironmentConfigLayer::new("TEST_FILE_")
+            .with_file_suffix("_FILE")
+            .with_env_source(both);
+
+        assert!(layer.read_value().is_err());
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let value = value_map! {
+            name: "app",
+            port: 8080,
+            debug: true,
+            log: value_map! { level: "info", path: "/var/log/app.log" },
+            hosts: value_vec!["a.com", "b.com"],
+            origins: value_vec!["x.com", "y.com"],
+            users: value_vec![value_map! { name: "root", admin: true }],
+            message: "hello \"world\"",
+        };
+
+        let layer = EnvironmentConfigLayer::new("APP_")
+            .with_separator("__")
+            .with_key_case(KeyCase::Lower)
+            .with_delimited_key("origins", ",")
+            .unwrap()
+            .with_infer_types(true);
+
+        let vars = layer.export_vars(&value);
+        assert!(vars.contains(&("APP_LOG__LEVEL".to_string(), "info".to_string())));
+        assert!(vars.contains(&("APP_HOSTS__1".to_string(), "b.com".to_string())));
+        assert!(vars.contains(&("APP_ORIGINS".to_string(), "x.com,y.com".to_string())));
+        assert!(vars.contains(&("APP_USERS__0__ADMIN".to_string(), "true".to_string())));
+
+        let read = layer
+            .clone()
+            .with_env_source(vars.into_iter().collect::<HashMap<_, _>>())
+            .read_value()
+            .unwrap();
+        assert_eq!(read, value);
+
+        let content = layer.export_dotenv(&value);
+        assert!(
+            content.contains("APP_MESSAGE=\"hello \\\"world\\\"\"\n"),
+            "{}",
+            content
+        );
+        let parsed = dotenv::vars(&dotenv::parse(&content).unwrap());
+        assert_eq!(parsed, layer.export_vars(&value));
+
+        let mut command = std::process::Command::new("env");
+        layer.export_to_command(&value, &mut command);
+        let envs: Vec<_> = command.get_envs().collect();
+        assert!(envs.contains(&("APP_PORT".as_ref(), Some("8080".as_ref()))));
+    }
+
+    #[test]
+    fn test_environment_layer_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+        assert_send_sync(&EnvironmentConfigLayer::new("APP_").with_env_source(env(&[])));
+    }
 }
@@ -1,18 +1,75 @@
-use std::path::PathBuf;
+use std::fs::{File, OpenOptions};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
-use super::FileHandler;
+use fs4::{FileExt, TryLockError};
+
+use super::{ConfigLayerError, FileHandler};
+use crate::model::ValuePath;
 use crate::{Result, Value};
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 /// A configuration layer that reads/write from a file.
+///
+/// Writes are atomic: the value is written to a temporary file in the same directory,
+/// synced to disk and renamed over the original, keeping its permissions. A crash
+/// mid-write leaves either the old or the new file, never a truncated one.
+///
+/// Reads and writes hold an advisory lock on a `<file>.lock` file next to it, so processes
+/// sharing the file don't clobber each other. Only writes create the lock file; reads
+/// without one, like of a read-only file, are done without a lock. A write fails with `StaleWrite` if the file
+/// changed since this layer last read it; use `update_value` to read, change and write
+/// the file while holding the lock.
+///
+/// The layer can be written if the file, or its directory when it doesn't exist, is
+/// writable, unless it's created with `read_only`.
 pub struct FileConfigLayer<T>
 where
     T: FileHandler,
 {
     path: PathBuf,
+    read_only: bool,
+    backups: usize,
+    lock_timeout: Duration,
+    /// Version of the file when it was last read or written by this layer
+    version: Mutex<Option<FileVersion>>,
     t: std::marker::PhantomData<T>,
 }
 
+/// Hash of the file content, `None` if the file doesn't exist.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FileVersion(Option<u64>);
+
+/// Advisory lock on the lock file of a layer, released when dropped. Holds no lock when
+/// reading without a lock file.
+pub(crate) struct FileLock(Option<File>);
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        if let Some(file) = &self.0 {
+            let _ = FileExt::unlock(file);
+        }
+    }
+}
+
+impl<T> Clone for FileConfigLayer<T>
+where
+    T: FileHandler,
+{
+    fn clone(&self) -> Self {
+        Self {
+            path: self.path.clone(),
+            read_only: self.read_only,
+            backups: self.backups,
+            lock_timeout: self.lock_timeout,
+            version: Mutex::new(self.get_version()),
+            t: std::marker::PhantomData,
+        }
+    }
+}
+
 impl<T> FileConfigLayer<T>
 where
     T: FileHandler,
@@ -20,17 +77,323 @@ where
     pub fn new(path: PathBuf) -> Self {
         Self {
             path,
+            read_only: false,
+            backups: 0,
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+            version: Mutex::new(None),
             t: std::marker::PhantomData,
         }
     }
 
+    /// Creates a layer that never writes to the file.
+    pub fn read_only(path: PathBuf) -> Self {
+        Self::new(path).with_read_only(true)
+    }
+
     pub fn get_path(&self) -> &PathBuf {
         &self.path
     }
 
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// When enabled, writes fail with `ConfigLayerError::WriteNotSupported`.
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    /// Like `set_read_only`, but returns `self` for method chaining.
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.set_read_only(read_only);
+        self
+    }
+
     pub fn exists(&self) -> bool {
         self.path.exists()
     }
+
+    pub fn get_backups(&self) -> usize {
+        self.backups
+    }
+
+    /// Sets how many copies of the previous file are kept when writing, `0` by default.
+    ///
+    /// Backups are named `<file>.1.bak` for the most recent up to `<file>.<n>.bak`.
+    pub fn set_backups(&mut self, backups: usize) {
+        self.backups = backups;
+    }
+
+    /// Like `set_backups`, but returns `self` for method chaining.
+    pub fn with_backups(mut self, backups: usize) -> Self {
+        self.set_backups(backups);
+        self
+    }
+
+    pub fn get_lock_timeout(&self) -> Duration {
+        self.lock_timeout
+    }
+
+    /// Sets how long to wait for the lock held by another process, 5 seconds by default.
+    pub fn set_lock_timeout(&mut self, timeout: Duration) {
+        self.lock_timeout = timeout;
+    }
+
+    /// Like `set_lock_timeout`, but returns `self` for method chaining.
+    pub fn with_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.set_lock_timeout(timeout);
+        self
+    }
+
+    pub fn get_lock_path(&self) -> PathBuf {
+        lock_path(&self.path)
+    }
+
+    /// Returns the path of the backup at `index`, `1` being the most recent.
+    pub fn get_backup_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".{}.bak", index));
+        self.path.with_file_name(name)
+    }
+
+    /// Returns the paths of the existing backups, the most recent first.
+    pub fn get_backup_paths(&self) -> Vec<PathBuf> {
+        (1..=self.backups)
+            .map(|i| self.get_backup_path(i))
+            .filter(|path| path.exists())
+            .collect()
+    }
+
+    /// Replaces the file with the backup at `index`, `1` being the most recent.
+    pub fn restore_backup(&self, index: usize) -> Result<()> {
+        let backup = self.get_backup_path(index);
+        if !backup.exists() {
+            return Err(ConfigLayerError::BackupNotFound(backup).into());
+        }
+
+        if !super::ConfigLayer::can_write(self) {
+            return Err(ConfigLayerError::WriteNotSupported(None).into());
+        }
+
+        log::trace!("Restoring backup: {}", backup.display());
+        let _lock = self.lock(false)?;
+        replace_file(&self.path, |temp| {
+            std::fs::copy(&backup, temp)?;
+            Ok(())
+        })?;
+        self.set_version(Some(self.read_version()?));
+        Ok(())
+    }
+
+    /// Waits for a shared or exclusive lock on the lock file, up to the lock timeout.
+    fn lock(&self, shared: bool) -> Result<FileLock> {
+        lock_file(&self.path, shared, self.lock_timeout)
+    }
+
+    fn get_version(&self) -> Option<FileVersion> {
+        *self.version.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn set_version(&self, version: Option<FileVersion>) {
+        *self.version.lock().unwrap_or_else(|e| e.into_inner()) = version;
+    }
+
+    fn read_version(&self) -> Result<FileVersion> {
+        if !self.path.exists() {
+            return Ok(FileVersion(None));
+        }
+
+        let mut hasher = DefaultHasher::new();
+        std::fs::read(&self.path)?.hash(&mut hasher);
+        Ok(FileVersion(Some(hasher.finish())))
+    }
+
+    /// Reads the file, the lock must be held.
+    fn read_locked(&self) -> Result<Value> {
+        self.set_version(Some(self.read_version()?));
+        if !self.path.exists() {
+            return Ok(Value::new_map());
+        }
+
+        T::read(self.get_path())
+    }
+
+    /// Writes the file, the lock must be held.
+    fn write_locked(&self, value: &Value) -> Result<()> {
+        self.rotate_backups()?;
+        replace_file(&self.path, |temp| T::write(temp, value))?;
+        self.set_version(Some(self.read_version()?));
+        Ok(())
+    }
+
+    /// Moves the backups one position up, dropping the oldest, and copies the file
+    /// to the most recent one.
+    fn rotate_backups(&self) -> Result<()> {
+        if self.backups == 0 || !self.path.exists() {
+            return Ok(());
+        }
+
+        for i in (1..self.backups).rev() {
+            let from = self.get_backup_path(i);
+            if from.exists() {
+                std::fs::rename(&from, self.get_backup_path(i + 1))?;
+            }
+        }
+
+        std::fs::copy(&self.path, self.get_backup_path(1))?;
+        Ok(())
+    }
+}
+
+/// Default time to wait for the lock of a file held by another process.
+pub(crate) const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Returns the path of the lock file of `path`, `<file>.lock`.
+pub(crate) fn lock_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    path.with_file_name(name)
+}
+
+/// Waits for a shared or exclusive lock on the lock file of `path`, up to `timeout`.
+///
+/// Exclusive locks create the lock file. Shared locks, used for reads, only open an existing
+/// one and read without a lock if it's missing or can't be opened, so reading doesn't need
+/// write access to the directory. Writes are atomic, so a read never sees a partial file.
+pub(crate) fn lock_file(path: &Path, shared: bool, timeout: Duration) -> Result<FileLock> {
+    let path = lock_path(path);
+    let file = if shared {
+        match File::open(&path) {
+            Ok(file) => file,
+            Err(e) => {
+                log::trace!("Reading without lock {}: {}", path.display(), e);
+                return Ok(FileLock(None));
+            }
+        }
+    } else {
+        OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)?
+    };
+
+    let start = Instant::now();
+    loop {
+        let result = if shared {
+            FileExt::try_lock_shared(&file)
+        } else {
+            FileExt::try_lock(&file)
+        };
+
+        match result {
+            Ok(()) => return Ok(FileLock(Some(file))),
+            Err(TryLockError::WouldBlock) if start.elapsed() < timeout => {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            Err(TryLockError::WouldBlock) => {
+                return Err(ConfigLayerError::LockTimeout { path, timeout }.into())
+            }
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+    }
+}
+
+/// Replaces the file at `path` atomically: writes a temporary file in the same directory
+/// with `write`, syncs it and renames it over the file, keeping its permissions.
+pub(crate) fn replace_file<F>(path: &Path, write: F) -> Result<()>
+where
+    F: FnOnce(&PathBuf) -> Result<()>,
+{
+    let temp = get_temp_path(path);
+    let result = write_temp(path, &temp, write).and_then(|_| {
+        std::fs::rename(&temp, path)?;
+        sync_parent(path);
+        Ok(())
+    });
+
+    if result.is_err() && temp.exists() {
+        let _ = std::fs::remove_file(&temp);
+    }
+
+    result
+}
+
+fn write_temp<F>(path: &Path, temp: &PathBuf, write: F) -> Result<()>
+where
+    F: FnOnce(&PathBuf) -> Result<()>,
+{
+    // Handlers that edit the existing file, like TOML, read it from the temporary file
+    if path.exists() {
+        std::fs::copy(path, temp)?;
+    }
+
+    write(temp)?;
+
+    if let Ok(metadata) = std::fs::metadata(path) {
+        std::fs::set_permissions(temp, metadata.permissions())?;
+    }
+
+    File::open(temp)?.sync_all()?;
+    Ok(())
+}
+
+fn get_temp_path(path: &Path) -> PathBuf {
+    let mut name = std::ffi::OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(".{}.tmp", std::process::id()));
+    path.with_file_name(name)
+}
+
+/// Returns true if the file at `path` can be replaced, which requires its directory to be
+/// writable, and the file too if it exists.
+///
+/// On Unix, this is checked with `faccessat(2)` for the effective user and group, which
+/// accounts for the owner, group and read-only mounts. Elsewhere, the file is opened for writing and the directory's
+/// read-only attribute is checked.
+pub(crate) fn is_writable(path: &Path) -> bool {
+    let parent = match path.parent() {
+        Some(parent) if parent.as_os_str().is_empty() => Path::new("."),
+        Some(parent) => parent,
+        None => return false,
+    };
+
+    has_write_access(parent) && (!path.exists() || has_write_access(path))
+}
+
+#[cfg(unix)]
+fn has_write_access(path: &Path) -> bool {
+    use rustix::fs::{accessat, Access, AtFlags, CWD};
+
+    accessat(CWD, path, Access::WRITE_OK, AtFlags::EACCESS).is_ok()
+}
+
+#[cfg(not(unix))]
+fn has_write_access(path: &Path) -> bool {
+    if path.is_dir() {
+        std::fs::metadata(path).is_ok_and(|metadata| !metadata.permissions().readonly())
+    } else {
+        OpenOptions::new().write(true).open(path).is_ok()
+    }
+}
+
+/// Syncs the directory of `path`, so the rename survives a crash. Not supported on Windows.
+fn sync_parent(path: &Path) {
+    #[cfg(unix)]
+    {
+        let parent = match path.parent() {
+            Some(parent) if parent.as_os_str().is_empty() => Path::new("."),
+            Some(parent) => parent,
+            None => return,
+        };
+
+        if let Err(e) = File::open(parent).and_then(|dir| dir.sync_all()) {
+            log::warn!("Failed to sync directory {}: {}", parent.display(), e);
+        }
+    }
+
+    #[cfg(not(unix))]
+    let _ = path;
 }
 
 impl<T> super::ConfigLayer for FileConfigLayer<T>
@@ -46,18 +409,186 @@ where
     }
 
     fn can_write(&self) -> bool {
-        false
+        !self.read_only && is_writable(&self.path)
     }
 
     fn write_value(&self, value: &Value) -> Result<()> {
-        T::write(&self.path, value)
+        if !self.can_write() {
+            return Err(ConfigLayerError::WriteNotSupported(None).into());
+        }
+
+        let _lock = self.lock(false)?;
+
+        if let Some(version) = self.get_version() {
+            if version != self.read_version()? {
+                return Err(ConfigLayerError::StaleWrite(self.path.clone()).into());
+            }
+        }
+
+        self.write_locked(value)
+    }
+
+    fn update_value(&self, update: &mut dyn FnMut(&mut Value) -> Result<()>) -> Result<()> {
+        if !self.can_write() {
+            return Err(ConfigLayerError::WriteNotSupported(None).into());
+        }
+
+        let _lock = self.lock(false)?;
+        let mut value = self.read_locked()?;
+        update(&mut value)?;
+        self.write_locked(&value)
+    }
+
+    fn get_source(&self, _path: &ValuePath) -> Option<String> {
+        Some(self.path.display().to_string())
     }
 
     fn read_value(&self) -> Result<Value> {
         if !self.path.exists() {
+            self.set_version(Some(FileVersion(None)));
             return Ok(Value::new_map());
         }
 
-        T::read(self.get_path())
+        let _lock = self.lock(true)?;
+        self.read_locked()
+    }
+}
+
+#[cfg(all(test, feature = "toml"))]
+mod tests {
+    use super::*;
+    use crate::config_layer::handlers::TomlFileHandler;
+    use crate::config_layer::ConfigLayer;
+    use crate::value_map;
+
+    #[test]
+    fn test_write_keeps_backups() {
+        let path = PathBuf::from("./target/test_file_backups.toml");
+        let layer = FileConfigLayer::<TomlFileHandler>::new(path.clone()).with_backups(2);
+        for backup in [layer.get_backup_path(1), layer.get_backup_path(2)] {
+            let _ = std::fs::remove_file(backup);
+        }
+        std::fs::write(&path, "# version 1\nversion = 1\n").unwrap();
+
+        for version in 2..=4 {
+            layer.write_value(&value_map! { version: version }).unwrap();
+        }
+
+        assert_eq!(layer.read_value().unwrap(), value_map! { version: 4 });
+        assert_eq!(layer.get_backup_paths().len(), 2);
+        assert_eq!(
+            std::fs::read_to_string(layer.get_backup_path(2)).unwrap(),
+            "# version 1\nversion = 2\n"
+        );
+
+        // Writing after a restore doesn't need a read first
+        layer.restore_backup(1).unwrap();
+        layer.write_value(&value_map! { version: 5 }).unwrap();
+        assert_eq!(layer.read_value().unwrap(), value_map! { version: 5 });
+
+        layer.restore_backup(1).unwrap();
+        assert_eq!(layer.read_value().unwrap(), value_map! { version: 3 });
+
+        let err = layer.restore_backup(3).unwrap_err();
+        assert!(err.to_string().contains("Backup not found"));
+    }
+
+    #[test]
+    fn test_write_detects_stale_read() {
+        let path = PathBuf::from("./target/test_file_stale.toml");
+        std::fs::write(&path, "count = 1\n").unwrap();
+
+        let cli = FileConfigLayer::<TomlFileHandler>::new(path.clone());
+        let daemon = FileConfigLayer::<TomlFileHandler>::new(path.clone());
+
+        let mut value = cli.read_value().unwrap();
+        daemon.write_value(&value_map! { count: 2 }).unwrap();
+
+        value.set("cli", Value::from(true)).unwrap();
+        let err = cli.write_value(&value).unwrap_err();
+        assert!(err.to_string().contains("was modified since it was read"));
+
+        cli.update_value(&mut |value| {
+            value.set("cli", Value::from(true))?;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(
+            daemon.read_value().unwrap(),
+            value_map! { count: 2, cli: true }
+        );
+    }
+
+    #[test]
+    fn test_file_layer_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+        let layer = FileConfigLayer::<TomlFileHandler>::new(PathBuf::from("./target/a.toml"));
+        assert_send_sync(&std::sync::Arc::new(layer));
+    }
+
+    #[test]
+    fn test_write_waits_for_lock() {
+        let path = PathBuf::from("./target/test_file_lock.toml");
+        std::fs::write(&path, "version = 0\n").unwrap();
+        let layer = FileConfigLayer::<TomlFileHandler>::new(path.clone())
+            .with_lock_timeout(Duration::from_millis(50));
+
+        let other = FileConfigLayer::<TomlFileHandler>::new(path);
+        let lock = other.lock(false).unwrap();
+
+        let err = layer.write_value(&value_map! { version: 1 }).unwrap_err();
+        assert!(err.to_string().contains("Timed out after 50ms"));
+        assert!(layer.read_value().is_err());
+
+        drop(lock);
+        layer.write_value(&value_map! { version: 1 }).unwrap();
+    }
+
+    #[test]
+    fn test_read_only_layer() {
+        let path = PathBuf::from("./target/test_file_read_only.toml");
+        std::fs::write(&path, "version = 1\n").unwrap();
+
+        let layer = FileConfigLayer::<TomlFileHandler>::read_only(path.clone());
+        let _ = std::fs::remove_file(layer.get_lock_path());
+        assert_eq!(layer.read_value().unwrap(), value_map! { version: 1 });
+        assert!(!layer.get_lock_path().exists());
+
+        assert!(!layer.can_write());
+        let err = layer.write_value(&value_map! { version: 2 }).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Config Layer Error: Writing to this layer is not supported"
+        );
+
+        let layer = FileConfigLayer::<TomlFileHandler>::new(path);
+        assert!(layer.can_write());
+
+        let layer =
+            FileConfigLayer::<TomlFileHandler>::new(PathBuf::from("./target/missing/a.toml"));
+        assert!(!layer.can_write());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = PathBuf::from("./target/test_file_permissions.toml");
+        std::fs::write(&path, "version = 1\n").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+
+        let layer = FileConfigLayer::<TomlFileHandler>::new(path.clone());
+        layer.write_value(&value_map! { version: 2 }).unwrap();
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(layer.read_value().unwrap(), value_map! { version: 2 });
+
+        // Only the group can write, which doesn't include the owner, unless it's root
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o460)).unwrap();
+        let is_root = rustix::process::geteuid().is_root();
+        assert_eq!(layer.can_write(), is_root);
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
     }
 }
@@ -27,7 +27,7 @@ impl super::ConfigLayer for MemoryConfigLayer {
     }
 
     fn write_value(&self, _value: &Value) -> Result<()> {
-        Err(ConfigLayerError::WriteNotSupported.into())
+        Err(ConfigLayerError::WriteNotSupported(None).into())
     }
 
     fn read_value(&self) -> Result<Value> {
@@ -1,15 +1,27 @@
+mod dotenv;
+mod env_source;
 mod error;
 mod file_handler;
+mod infer;
 mod layer;
+mod layer_args;
+#[cfg(feature = "clap")]
+mod layer_clap;
+mod layer_dotenv;
 mod layer_env;
 mod layer_file;
 mod layer_mem;
 
 pub mod handlers;
 
+pub use env_source::{EnvSnapshot, EnvSource, ProcessEnv};
 pub use error::ConfigLayerError;
 pub use file_handler::FileHandler;
 pub use layer::ConfigLayer;
-pub use layer_env::EnvironmentConfigLayer;
+pub use layer_args::ArgsConfigLayer;
+#[cfg(feature = "clap")]
+pub use layer_clap::{ClapConfigLayer, CONFIG_ARG_ID};
+pub use layer_dotenv::DotenvConfigLayer;
+pub use layer_env::{EnvMapping, EnvironmentConfigLayer, KeyCase};
 pub use layer_file::FileConfigLayer;
 pub use layer_mem::MemoryConfigLayer;
@@ -2,7 +2,9 @@
 macro_rules! create_config {
     ($file_name:literal {
         default: { $($key:ident: $value:expr),* $(,)? },
-        $(env: $env_prefix:literal,)?
+        $(env: $($env_prefix:literal)|+,)?
+        $(env_map: { $($env_var:literal => $env_key:literal $(as $env_type:ident)?),* $(,)? },)?
+        $(env_source: $env_source:expr,)?
         $($layer:literal: [$($layer_dir_name:ident$(($layer_name_or_path:literal))?),*] $(init: $layer_init:literal)?),*
     }) => {
         {
@@ -30,7 +32,10 @@ macro_rules! create_config {
 
                     $(
                         if added && !path.exists() && $layer_init {
-                            if let Some(config_layer) = config.get_layer($layer) {
+                            let config_layer = config
+                                .get_layer($layer)
+                                .filter(|layer| layer.can_write());
+                            if let Some(config_layer) = config_layer {
                                 config_layer.write_value(
                                     &default_value
                                 ).unwrap_or_else(|e| {
@@ -44,10 +49,35 @@ macro_rules! create_config {
                 }
             )*
 
+            let env_layer: Option<$crate::config_layer::EnvironmentConfigLayer> = None
+                $(.or(Some(
+                    $crate::config_layer::EnvironmentConfigLayer::unprefixed()
+                        $(.with_prefix($env_prefix))+
+                )))?;
+
+            $(
+                let env_layer = {
+                    let mut layer = env_layer
+                        .unwrap_or_else($crate::config_layer::EnvironmentConfigLayer::unprefixed);
+                    $(
+                        let value_type: Option<$crate::model::ValueType> = None
+                            $(.or(Some($crate::model::ValueType::$env_type)))?;
+                        if let Err(e) = layer.add_mapping($env_var, $env_key, value_type) {
+                            log::warn!("Error mapping environment variable {}: {:?}", $env_var, e);
+                        }
+                    )*
+                    Some(layer)
+                };
+            )?
+
             $(
-                config.add_environment_layer("environment", $env_prefix);
+                let env_layer = env_layer.map(|layer| layer.with_env_source($env_source));
             )?
 
+            if let Some(layer) = env_layer {
+                config.push_layer("environment", Box::new(layer));
+            }
+
             config
         }
     };
@@ -95,6 +125,34 @@ mod tests {
         assert_eq!(v.get("data").try_into_i64().unwrap(), 11);
     }
 
+    #[test]
+    fn add_environment_mappings() {
+        let env = std::collections::HashMap::from([
+            ("DATABASE_URL".to_string(), "postgres://db".to_string()),
+            ("PORT".to_string(), "8080".to_string()),
+            ("OTHER_name".to_string(), "other".to_string()),
+        ]);
+
+        let config = create_config!(".app-config" {
+            default: { port: 80, name: "app" },
+            env: "APP_" | "OTHER_",
+            env_map: {
+                "DATABASE_URL" => "database.url",
+                "PORT" => "port" as Integer,
+            },
+            env_source: env,
+        });
+
+        let v = config.build_value(false).unwrap();
+
+        assert_eq!(
+            v.get("database").get("url").try_into_string().unwrap(),
+            "postgres://db"
+        );
+        assert_eq!(v.get("port"), crate::Value::Integer(8080));
+        assert_eq!(v.get("name").try_into_string().unwrap(), "other");
+    }
+
     #[test]
     fn initiliaze_file() {
         std::env::set_var("MY_APP_data", "11");
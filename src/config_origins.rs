@@ -0,0 +1,333 @@
+//! Tracks which layer supplied each value of a built configuration.
+
+use std::collections::HashMap;
+
+use crate::config_layer::ConfigLayer;
+use crate::model::{MergeOptions, MergeStrategy, ValuePath};
+use crate::Value;
+
+/// A value set by a configuration layer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueOrigin {
+    /// Name of the layer in the configuration.
+    pub layer: &'static str,
+    /// Kind of the layer, as returned by `ConfigLayer::get_name`.
+    pub kind: &'static str,
+    /// File path, environment variable or other source reported by the layer.
+    pub source: Option<String>,
+    /// Value set by the layer, `None` if the layer removed the value.
+    pub value: Option<Value>,
+    /// True if the value was merged with the value of the lower layers instead of
+    /// replacing it, like an array with the `Append` strategy.
+    pub merged: bool,
+}
+
+impl std::fmt::Display for ValueOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.source {
+            Some(source) => write!(f, "'{}' ({}: {})", self.layer, self.kind, source),
+            None => write!(f, "'{}' ({})", self.layer, self.kind),
+        }
+    }
+}
+
+/// A configuration value built from multiple layers, with the origin of each leaf value.
+///
+/// Leaf values are any value that is not a non-empty map, so arrays are tracked as a whole.
+/// An array merged with a strategy other than `Replace` has every layer that added
+/// elements to it as origin, see `get_contributors`.
+#[derive(Debug, Clone)]
+pub struct AnnotatedValue {
+    value: Value,
+    origins: HashMap<ValuePath, Vec<ValueOrigin>>,
+}
+
+impl AnnotatedValue {
+    pub(crate) fn new() -> Self {
+        Self {
+            value: Value::new_map(),
+            origins: HashMap::new(),
+        }
+    }
+
+    /// Merges the value of a layer and records it as the origin of its leaf values.
+    pub(crate) fn merge_layer(
+        &mut self,
+        name: &'static str,
+        layer: &dyn ConfigLayer,
+        layer_value: &Value,
+        options: &MergeOptions,
+    ) {
+        let mut leaves = Vec::new();
+        collect_leaves(layer_value, &mut ValuePath::root(), options, &mut leaves);
+
+        let origins: Vec<(ValuePath, ValueOrigin)> = leaves
+            .into_iter()
+            .map(|(path, value)| {
+                let merged = self.merges_array(&path, value.as_ref(), options);
+                let origin = ValueOrigin {
+                    layer: name,
+                    kind: layer.get_name(),
+                    source: layer.get_source(&path),
+                    value,
+                    merged,
+                };
+                (path, origin)
+            })
+            .collect();
+
+        self.value.merge_with(layer_value, options);
+
+        for (path, origin) in origins {
+            self.origins.entry(path).or_default().push(origin);
+        }
+    }
+
+    /// Returns true if `value` is an array that will be merged with the current array at
+    /// `path`, instead of replacing it.
+    fn merges_array(
+        &self,
+        path: &ValuePath,
+        value: Option<&Value>,
+        options: &MergeOptions,
+    ) -> bool {
+        matches!(value, Some(Value::Array(_)))
+            && *options.strategy_for(path) != MergeStrategy::Replace
+            && matches!(self.value.get_path(path), Ok(Some(Value::Array(_))))
+    }
+
+    /// The merged value, same as returned by `BakuninConfig::build_value`.
+    pub fn get_value(&self) -> &Value {
+        &self.value
+    }
+
+    pub fn into_value(self) -> Value {
+        self.value
+    }
+
+    /// Every value set for `path`, from the lowest layer to the highest.
+    pub fn get_origins(&self, path: &ValuePath) -> &[ValueOrigin] {
+        self.origins.get(path).map(|o| o.as_slice()).unwrap_or(&[])
+    }
+
+    /// The layer that supplied the effective value at `path`, the highest one if the value
+    /// is an array merged from multiple layers.
+    ///
+    /// Returns `None` if the path is missing or was replaced by a map in a higher layer.
+    pub fn get_origin(&self, path: &ValuePath) -> Option<&ValueOrigin> {
+        match self.value.get_path(path) {
+            Ok(Some(Value::Map(map))) if !map.is_empty() => None,
+            Ok(Some(_)) => self
+                .get_origins(path)
+                .last()
+                .filter(|origin| origin.value.is_some()),
+            _ => None,
+        }
+    }
+
+    /// Every layer that supplied part of the effective value at `path`, from the lowest to
+    /// the highest. Only arrays merged with a strategy other than `Replace` have more than
+    /// one contributor; each origin holds the elements its layer added.
+    pub fn get_contributors(&self, path: &ValuePath) -> &[ValueOrigin] {
+        let origins = self.get_origins(path);
+        if self.get_origin(path).is_none() {
+            return &[];
+        }
+
+        let first = origins
+            .iter()
+            .rposition(|origin| !origin.merged)
+            .unwrap_or(0);
+        &origins[first..]
+    }
+
+    /// Values from lower layers that were overridden by the effective value at `path`.
+    pub fn get_shadowed(&self, path: &ValuePath) -> &[ValueOrigin] {
+        let origins = self.get_origins(path);
+        &origins[..origins.len() - self.get_contributors(path).len()]
+    }
+
+    /// All paths with recorded origins, sorted.
+    pub fn paths(&self) -> Vec<&ValuePath> {
+        let mut paths: Vec<&ValuePath> = self.origins.keys().collect();
+        paths.sort_by_key(|p| p.to_string());
+        paths
+    }
+
+    /// Describes the override chain of `path`, from the effective value to the lowest layer.
+    pub fn explain(&self, path: &ValuePath) -> String {
+        let mut lines = Vec::new();
+
+        let contributors = self.get_contributors(path);
+        match contributors {
+            [] => lines.push(format!("{} is not set", path)),
+            [origin] => {
+                let value = origin.value.as_ref().unwrap_or(&Value::None);
+                lines.push(format!("{} = {}", path, value));
+                lines.push(format!("  set by {}", origin));
+            }
+            _ => {
+                let value = self
+                    .value
+                    .get_path(path)
+                    .ok()
+                    .flatten()
+                    .unwrap_or(&Value::None);
+                lines.push(format!("{} = {}", path, value));
+                for origin in contributors.iter().rev() {
+                    let value = origin.value.as_ref().unwrap_or(&Value::None);
+                    lines.push(format!("  merges {}: {}", origin, value));
+                }
+            }
+        }
+
+        for origin in self.get_shadowed(path).iter().rev() {
+            match &origin.value {
+                Some(value) => lines.push(format!("  overrides {}: {}", origin, value)),
+                None => lines.push(format!("  removed by {}", origin)),
+            }
+        }
+
+        lines.join("\n")
+    }
+}
+
+/// Collects the leaf values of a layer, removed paths are collected with a `None` value.
+fn collect_leaves(
+    value: &Value,
+    path: &mut ValuePath,
+    options: &MergeOptions,
+    leaves: &mut Vec<(ValuePath, Option<Value>)>,
+) {
+    match value {
+        Value::Map(map) if !map.is_empty() => {
+            for unset_path in options.unset_paths(map) {
+                leaves.push((path.join(&unset_path), None));
+            }
+
+            for (key, value) in map {
+                if options.is_directive(key) {
+                    continue;
+                }
+
+                path.push_key(key.as_str());
+                if options.is_unset(value) {
+                    leaves.push((path.clone(), None));
+                } else {
+                    collect_leaves(value, path, options, leaves);
+                }
+                path.pop();
+            }
+        }
+        _ if path.is_root() => {}
+        _ => leaves.push((path.clone(), Some(options.strip_unset(value)))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::config_layer::EnvironmentConfigLayer;
+    use crate::model::{MergeOptions, MergeStrategy, ValuePath};
+    use crate::{value_map, value_vec, BakuninConfig};
+
+    fn config() -> BakuninConfig {
+        let env = HashMap::from([("ORIGIN_TEST_port".to_string(), "9090".to_string())]);
+
+        BakuninConfig::new()
+            .with_memory_layer(
+                "default",
+                value_map! {
+                    log: value_map! { level: "info", file: "app.log" },
+                    port: 80,
+                    hosts: value_vec!["a"],
+                },
+            )
+            .with_memory_layer(
+                "local",
+                value_map! {
+                    log: value_map! { level: "debug", file: "!unset" },
+                },
+            )
+            .with_layer(
+                "env",
+                Box::new(EnvironmentConfigLayer::new("ORIGIN_TEST_").with_env_source(env)),
+            )
+            .with_merge_options(MergeOptions::default().with_unset_sentinel(Some("!unset".into())))
+    }
+
+    #[test]
+    fn test_records_origins() {
+        let annotated = config().build_with_origins(false).unwrap();
+        let level = ValuePath::parse("log.level").unwrap();
+
+        let origin = annotated.get_origin(&level).unwrap();
+        assert_eq!(origin.layer, "local");
+        assert_eq!(origin.kind, "Memory");
+        assert_eq!(origin.value, Some("debug".into()));
+
+        let shadowed = annotated.get_shadowed(&level);
+        assert_eq!(shadowed.len(), 1);
+        assert_eq!(shadowed[0].layer, "default");
+        assert_eq!(shadowed[0].value, Some("info".into()));
+
+        let port = annotated
+            .get_origin(&ValuePath::parse("port").unwrap())
+            .unwrap();
+        assert_eq!(port.layer, "env");
+        assert_eq!(port.kind, "Environment");
+        assert_eq!(port.source.as_deref(), Some("ORIGIN_TEST_port"));
+
+        let file = ValuePath::parse("log.file").unwrap();
+        assert!(annotated.get_origin(&file).is_none());
+        assert_eq!(annotated.get_shadowed(&file).len(), 2);
+
+        assert_eq!(annotated.get_value(), &config().build_value(false).unwrap());
+    }
+
+    #[test]
+    fn test_explains_path() {
+        let config = config();
+
+        assert_eq!(
+            config.explain("log.level").unwrap(),
+            "log.level = debug\n  set by 'local' (Memory)\n  overrides 'default' (Memory): info"
+        );
+        assert_eq!(
+            config.explain("log.file").unwrap(),
+            "log.file is not set\n  removed by 'local' (Memory)\n  overrides 'default' (Memory): app.log"
+        );
+        assert_eq!(config.explain("missing").unwrap(), "missing is not set");
+    }
+
+    #[test]
+    fn test_records_merged_array_origins() {
+        let config = config()
+            .with_memory_layer("user", value_map! { hosts: value_vec!["b"] })
+            .with_memory_layer("session", value_map! { hosts: value_vec!["c"] })
+            .with_path_merge_strategy("hosts", MergeStrategy::Append)
+            .unwrap();
+        let hosts = ValuePath::parse("hosts").unwrap();
+
+        let annotated = config.build_with_origins(false).unwrap();
+        let layers: Vec<&str> = annotated
+            .get_contributors(&hosts)
+            .iter()
+            .map(|origin| origin.layer)
+            .collect();
+        assert_eq!(layers, vec!["default", "user", "session"]);
+        assert_eq!(annotated.get_origin(&hosts).unwrap().layer, "session");
+        assert!(annotated.get_shadowed(&hosts).is_empty());
+
+        assert_eq!(
+            config.explain("hosts").unwrap(),
+            "hosts = [a, b, c]\n  merges 'session' (Memory): [c]\n  merges 'user' (Memory): [b]\n  merges 'default' (Memory): [a]"
+        );
+
+        let config = config.with_memory_layer("reset", value_map! { hosts: "none" });
+        let annotated = config.build_with_origins(false).unwrap();
+        assert_eq!(annotated.get_contributors(&hosts).len(), 1);
+        assert_eq!(annotated.get_shadowed(&hosts).len(), 3);
+    }
+}
@@ -62,4 +62,23 @@ mod tests {
 
         assert_eq!(res.path, expected);
     }
+
+    #[test]
+    fn finds_dotenv_in_parents() {
+        let root = std::env::current_dir()
+            .unwrap()
+            .join("target/test_find_dotenv");
+        let dir = root.join("app/src");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(root.join(".env"), "PORT=80\n").unwrap();
+
+        let res = FileFinder::new(".env")
+            .with_directory_and_parents(&dir)
+            .with_dotenv()
+            .find_first(false)
+            .unwrap();
+
+        assert_eq!(res.path, root.join(".env"));
+        assert!(res.extension.match_path(&res.path));
+    }
 }
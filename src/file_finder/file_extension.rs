@@ -3,19 +3,34 @@
 #[derive(Debug, Clone)]
 pub enum FileExtension {
     Custom(&'static str),
+    /// Dotenv files, like `.env` or `.env.local`, found with `FileFinder::new(".env")`.
+    Dotenv,
     #[cfg(feature = "toml")]
     Toml,
     #[cfg(feature = "json")]
     Json,
+    #[cfg(feature = "json5")]
+    Json5,
+    #[cfg(feature = "json5")]
+    Jsonc,
+    #[cfg(feature = "yaml")]
+    Yaml,
 }
 
 impl From<&'static str> for FileExtension {
     fn from(ext: &'static str) -> Self {
         match ext {
+            "" => FileExtension::Dotenv,
             #[cfg(feature = "toml")]
-            ".toml" => FileExtension::Toml,
+            "toml" => FileExtension::Toml,
             #[cfg(feature = "json")]
-            ".json" => FileExtension::Json,
+            "json" => FileExtension::Json,
+            #[cfg(feature = "json5")]
+            "json5" => FileExtension::Json5,
+            #[cfg(feature = "json5")]
+            "jsonc" => FileExtension::Jsonc,
+            #[cfg(feature = "yaml")]
+            "yaml" | "yml" => FileExtension::Yaml,
             _ => FileExtension::Custom(ext),
         }
     }
@@ -31,20 +46,37 @@ impl FileExtension {
     pub fn as_str(&self) -> &'static str {
         match self {
             FileExtension::Custom(ext) => ext,
+            FileExtension::Dotenv => "",
             #[cfg(feature = "toml")]
             FileExtension::Toml => "toml",
             #[cfg(feature = "json")]
             FileExtension::Json => "json",
+            #[cfg(feature = "json5")]
+            FileExtension::Json5 => "json5",
+            #[cfg(feature = "json5")]
+            FileExtension::Jsonc => "jsonc",
+            #[cfg(feature = "yaml")]
+            FileExtension::Yaml => "yaml",
         }
     }
 
     pub fn match_path<P: AsRef<std::path::Path>>(&self, path: P) -> bool {
         let ext = path.as_ref().extension().and_then(|s| s.to_str());
         match self {
+            FileExtension::Dotenv => {
+                let name = path.as_ref().file_name().and_then(|s| s.to_str());
+                name.is_some_and(|n| n == ".env" || n.starts_with(".env.")) || ext == Some("env")
+            }
             #[cfg(feature = "toml")]
             FileExtension::Toml => ext == Some("toml"),
             #[cfg(feature = "json")]
             FileExtension::Json => ext == Some("json"),
+            #[cfg(feature = "json5")]
+            FileExtension::Json5 => ext == Some("json5"),
+            #[cfg(feature = "json5")]
+            FileExtension::Jsonc => ext == Some("jsonc"),
+            #[cfg(feature = "yaml")]
+            FileExtension::Yaml => matches!(ext, Some("yaml") | Some("yml")),
             _ => false,
         }
     }
@@ -62,7 +94,31 @@ impl super::FileFinder {
     pub fn with_json(self) -> Self {
         self.with_extension(FileExtension::Json.as_str())
     }
-    
+
+    #[cfg(feature = "json5")]
+    /// Search for JSON5 files.
+    pub fn with_json5(self) -> Self {
+        self.with_extension(FileExtension::Json5.as_str())
+    }
+
+    #[cfg(feature = "json5")]
+    /// Search for JSON files with comments (JSONC).
+    pub fn with_jsonc(self) -> Self {
+        self.with_extension(FileExtension::Jsonc.as_str())
+    }
+
+    #[cfg(feature = "yaml")]
+    /// Search for YAML files, with both `.yaml` and `.yml` extensions.
+    pub fn with_yaml(self) -> Self {
+        self.with_extension(FileExtension::Yaml.as_str())
+            .with_extension("yml")
+    }
+
+    /// Search for dotenv files without extension, like `.env` with `FileFinder::new(".env")`.
+    pub fn with_dotenv(self) -> Self {
+        self.with_extension(FileExtension::Dotenv.as_str())
+    }
+
     /// Add all built-in supported extensions.
     pub fn with_supported_extensions(self) -> Self {
         let mut finder = self;
@@ -74,6 +130,38 @@ impl super::FileFinder {
         {
             finder = finder.with_json();
         }
+        #[cfg(feature = "json5")]
+        {
+            finder = finder.with_json5().with_jsonc();
+        }
+        #[cfg(feature = "yaml")]
+        {
+            finder = finder.with_yaml();
+        }
         finder
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_undotted_extension() {
+        assert!(matches!(FileExtension::from(""), FileExtension::Dotenv));
+        assert!(matches!(
+            FileExtension::from(".toml"),
+            FileExtension::Custom(".toml")
+        ));
+        #[cfg(feature = "toml")]
+        assert!(matches!(FileExtension::from("toml"), FileExtension::Toml));
+        #[cfg(feature = "json")]
+        assert!(matches!(FileExtension::from("json"), FileExtension::Json));
+        #[cfg(feature = "json5")]
+        assert!(matches!(FileExtension::from("json5"), FileExtension::Json5));
+        #[cfg(feature = "json5")]
+        assert!(matches!(FileExtension::from("jsonc"), FileExtension::Jsonc));
+        #[cfg(feature = "yaml")]
+        assert!(matches!(FileExtension::from("yml"), FileExtension::Yaml));
+    }
+}
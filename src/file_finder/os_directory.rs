@@ -79,6 +79,25 @@ impl super::FileFinder {
         self.with_os_directory(OSDirectory::WorkingDirectory)
     }
 
+    /// Adds the current working directory and all its parents to the list of directories
+    /// to search in, from the closest one to the root.
+    pub fn with_working_directory_and_parents(self) -> Self {
+        match std::env::current_dir() {
+            Ok(dir) => self.with_directory_and_parents(&dir),
+            Err(e) => {
+                log::warn!("Failed to resolve working directory: {:?}", e);
+                self
+            }
+        }
+    }
+
+    /// Adds `dir` and all its parents to the list of directories to search in, from the
+    /// closest one to the root.
+    pub fn with_directory_and_parents(mut self, dir: &std::path::Path) -> Self {
+        self.folders.extend(dir.ancestors().map(PathBuf::from));
+        self
+    }
+
     /// Adds the user's home directory to the list of directories to search in.
     pub fn with_user_home(self) -> Self {
         self.with_os_directory(OSDirectory::UserHome)
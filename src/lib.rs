@@ -7,9 +7,10 @@ pub mod model;
 pub mod bak_error;
 
 pub mod config;
+pub mod config_origins;
 
 pub mod config_macro;
 
 pub use bak_error::{BakuninError, Result};
-pub use config::BakuninConfig;
+pub use config::{BakuninConfig, SaveMode, SchemaPolicy};
 pub use model::Value;
@@ -0,0 +1,150 @@
+//! Conversion between `Value::Bytes` and its string representations.
+//!
+//! Bytes are written as base64 strings. When reading bytes from a string, a `base64:` or
+//! `hex:` prefix selects the encoding, otherwise the default `BytesEncoding` is used.
+
+use base64::{
+    alphabet,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    Engine,
+};
+
+use super::{ModelError, Value};
+
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Encoding used to read bytes from strings without an encoding prefix.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BytesEncoding {
+    #[default]
+    Base64,
+    Hex,
+}
+
+impl BytesEncoding {
+    pub fn encode(&self, bytes: &[u8]) -> String {
+        match self {
+            BytesEncoding::Base64 => encode_base64(bytes),
+            BytesEncoding::Hex => bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+        }
+    }
+
+    pub fn decode(&self, value: &str) -> Result<Vec<u8>, ModelError> {
+        match self {
+            BytesEncoding::Base64 => BASE64
+                .decode(value.trim())
+                .map_err(|_| conversion_error!(String, Bytes, "invalid base64 string")),
+            BytesEncoding::Hex => decode_hex(value.trim()),
+        }
+    }
+}
+
+/// Encodes bytes as a standard base64 string, with padding.
+pub fn encode_base64(bytes: &[u8]) -> String {
+    BASE64.encode(bytes)
+}
+
+/// Decodes a string with an optional `base64:` or `hex:` prefix, using `default` if none.
+pub fn decode_bytes(value: &str, default: BytesEncoding) -> Result<Vec<u8>, ModelError> {
+    if let Some(value) = value.strip_prefix("base64:") {
+        BytesEncoding::Base64.decode(value)
+    } else if let Some(value) = value.strip_prefix("hex:") {
+        BytesEncoding::Hex.decode(value)
+    } else {
+        default.decode(value)
+    }
+}
+
+fn decode_hex(value: &str) -> Result<Vec<u8>, ModelError> {
+    if !value.len().is_multiple_of(2) || !value.is_ascii() {
+        return err_conversion_error!(String, Bytes, "invalid hex string");
+    }
+
+    (0..value.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&value[i..i + 2], 16)
+                .map_err(|_| conversion_error!(String, Bytes, "invalid hex string"))
+        })
+        .collect()
+}
+
+impl Value {
+    /// Converts the value to bytes, decoding strings with `encoding` unless prefixed.
+    ///
+    /// Arrays are converted if all elements are numbers between 0 and 255.
+    pub fn try_into_bytes_with(&self, encoding: BytesEncoding) -> Result<Vec<u8>, ModelError> {
+        match self {
+            Value::Bytes(bytes) => Ok(bytes.clone()),
+            Value::String(value) => decode_bytes(value, encoding),
+            Value::Array(array) => array
+                .iter()
+                .map(|v| {
+                    v.try_into_u8()
+                        .map_err(|_| conversion_error!(Array, Bytes, "elements must be bytes"))
+                })
+                .collect(),
+            Value::None => err_conversion_error!(None, Bytes),
+            Value::Map(_) => err_conversion_error!(Map, Bytes),
+            _ => err_conversion_error!(Number, Bytes),
+        }
+    }
+
+    /// Converts the value to bytes, strings are decoded as base64 unless prefixed.
+    pub fn try_into_bytes(&self) -> Result<Vec<u8>, ModelError> {
+        self.try_into_bytes_with(BytesEncoding::default())
+    }
+}
+
+impl TryInto<Vec<u8>> for Value {
+    type Error = ModelError;
+
+    fn try_into(self) -> Result<Vec<u8>, Self::Error> {
+        match self {
+            Value::Bytes(bytes) => Ok(bytes),
+            value => value.try_into_bytes(),
+        }
+    }
+}
+
+impl From<&[u8]> for Value {
+    fn from(value: &[u8]) -> Self {
+        Self::Bytes(value.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::value_vec;
+
+    use super::*;
+
+    #[test]
+    fn test_model_value_try_into_bytes() {
+        assert_eq!(Value::Bytes(vec![1, 2]).try_into_bytes().unwrap(), vec![1, 2]);
+        assert_eq!(Value::from("AQI=").try_into_bytes().unwrap(), vec![1, 2]);
+        assert_eq!(Value::from("AQI").try_into_bytes().unwrap(), vec![1, 2]);
+        assert_eq!(Value::from("hex:01ff").try_into_bytes().unwrap(), vec![1, 255]);
+        assert_eq!(Value::from("base64:AQI=").try_into_bytes().unwrap(), vec![1, 2]);
+        assert_eq!(value_vec![1, 255].try_into_bytes().unwrap(), vec![1, 255]);
+        assert_eq!(
+            Value::from("01FF").try_into_bytes_with(BytesEncoding::Hex).unwrap(),
+            vec![1, 255]
+        );
+
+        assert!(Value::from("hex:0").try_into_bytes().is_err());
+        assert!(Value::from("!!").try_into_bytes().is_err());
+        assert!(value_vec![256].try_into_bytes().is_err());
+        assert!(Value::Integer(1).try_into_bytes().is_err());
+    }
+
+    #[test]
+    fn test_model_value_bytes_to_string() {
+        let value: String = Value::from(&[1u8, 2][..]).try_into().unwrap();
+        assert_eq!(value, "AQI=");
+        assert_eq!(BytesEncoding::Hex.encode(&[1, 255]), "01ff");
+    }
+}
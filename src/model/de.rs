@@ -4,16 +4,60 @@ use std::{
     vec::IntoIter,
 };
 
-use super::{ModelError, Value};
+use super::{BytesEncoding, ModelError, Value};
+
+/// Options used when deserializing a `Value`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeserializeOptions {
+    /// Match enum variant names ignoring ASCII case.
+    pub case_insensitive_enums: bool,
+    /// Encoding of strings deserialized as bytes, if they have no `base64:` or `hex:` prefix.
+    pub bytes_encoding: BytesEncoding,
+}
 
-impl<'de> serde::de::Deserializer<'de> for Value {
+impl DeserializeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Like setting `case_insensitive_enums`, but returns `self` for method chaining.
+    pub fn with_case_insensitive_enums(mut self, value: bool) -> Self {
+        self.case_insensitive_enums = value;
+        self
+    }
+
+    /// Like setting `bytes_encoding`, but returns `self` for method chaining.
+    pub fn with_bytes_encoding(mut self, encoding: BytesEncoding) -> Self {
+        self.bytes_encoding = encoding;
+        self
+    }
+}
+
+/// Deserializer for a `Value` that applies `DeserializeOptions` to every nested value.
+pub struct ValueDeserializer {
+    value: Value,
+    options: DeserializeOptions,
+}
+
+impl ValueDeserializer {
+    pub fn new(value: Value) -> Self {
+        Self::with_options(value, DeserializeOptions::default())
+    }
+
+    pub fn with_options(value: Value, options: DeserializeOptions) -> Self {
+        Self { value, options }
+    }
+}
+
+impl<'de> serde::de::Deserializer<'de> for ValueDeserializer {
     type Error = ModelError;
 
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        match self {
+        let options = self.options;
+        match self.value {
             Value::None => visitor.visit_unit(),
             Value::Boolean(value) => visitor.visit_bool(value),
             Value::Integer(value) => visitor.visit_i64(value),
@@ -22,8 +66,9 @@ impl<'de> serde::de::Deserializer<'de> for Value {
             Value::ULongInteger(value) => visitor.visit_u128(value),
             Value::Float(value) => visitor.visit_f64(value),
             Value::String(value) => visitor.visit_str(&value),
-            Value::Array(value) => visitor.visit_seq(ValueSeq::new(value)),
-            Value::Map(value) => visitor.visit_map(ValueMap::new(value)),
+            Value::Bytes(value) => visitor.visit_byte_buf(value),
+            Value::Array(value) => visitor.visit_seq(ValueSeq::new(value, options)),
+            Value::Map(value) => visitor.visit_map(ValueMap::new(value, options)),
         }
     }
 
@@ -31,70 +76,70 @@ impl<'de> serde::de::Deserializer<'de> for Value {
     where
         V: serde::de::Visitor<'de>,
     {
-        visitor.visit_bool(self.try_into()?)
+        visitor.visit_bool(self.value.try_into()?)
     }
 
     fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        visitor.visit_i8(self.try_into()?)
+        visitor.visit_i8(self.value.try_into()?)
     }
 
     fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        visitor.visit_i16(self.try_into()?)
+        visitor.visit_i16(self.value.try_into()?)
     }
 
     fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        visitor.visit_i32(self.try_into()?)
+        visitor.visit_i32(self.value.try_into()?)
     }
 
     fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        visitor.visit_i64(self.try_into()?)
+        visitor.visit_i64(self.value.try_into()?)
     }
 
     fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        visitor.visit_u8(self.try_into()?)
+        visitor.visit_u8(self.value.try_into()?)
     }
 
     fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        visitor.visit_u16(self.try_into()?)
+        visitor.visit_u16(self.value.try_into()?)
     }
 
     fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        visitor.visit_u32(self.try_into()?)
+        visitor.visit_u32(self.value.try_into()?)
     }
 
     fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        visitor.visit_u64(self.try_into()?)
+        visitor.visit_u64(self.value.try_into()?)
     }
 
     fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        let v: f64 = self.try_into()?;
+        let v: f64 = self.value.try_into()?;
         visitor.visit_f32(v as f32)
     }
 
@@ -102,21 +147,21 @@ impl<'de> serde::de::Deserializer<'de> for Value {
     where
         V: serde::de::Visitor<'de>,
     {
-        visitor.visit_f64(self.try_into()?)
+        visitor.visit_f64(self.value.try_into()?)
     }
 
     fn deserialize_char<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        visitor.visit_char(self.try_into()?)
+        visitor.visit_char(self.value.try_into()?)
     }
 
     fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        let val: String = self.try_into()?;
+        let val: String = self.value.try_into()?;
         visitor.visit_str(&val)
     }
 
@@ -124,28 +169,32 @@ impl<'de> serde::de::Deserializer<'de> for Value {
     where
         V: serde::de::Visitor<'de>,
     {
-        visitor.visit_string(self.try_into()?)
+        visitor.visit_string(self.value.try_into()?)
     }
 
-    fn deserialize_bytes<V>(self, _: V) -> Result<V::Value, Self::Error>
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        unimplemented!()
+        self.deserialize_byte_buf(visitor)
     }
 
-    fn deserialize_byte_buf<V>(self, _: V) -> Result<V::Value, Self::Error>
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        unimplemented!()
+        let bytes = match self.value {
+            Value::Bytes(bytes) => bytes,
+            value => value.try_into_bytes_with(self.options.bytes_encoding)?,
+        };
+        visitor.visit_byte_buf(bytes)
     }
 
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        match self {
+        match self.value {
             Value::None => visitor.visit_none(),
             _ => visitor.visit_some(self),
         }
@@ -155,9 +204,9 @@ impl<'de> serde::de::Deserializer<'de> for Value {
     where
         V: serde::de::Visitor<'de>,
     {
-        match self {
+        match self.value {
             Value::None => visitor.visit_unit(),
-            _ => ModelError::deserialization(self.to_string(), "None").to_result(),
+            value => ModelError::deserialization(value.to_string(), "None").to_result(),
         }
     }
 
@@ -187,9 +236,13 @@ impl<'de> serde::de::Deserializer<'de> for Value {
     where
         V: serde::de::Visitor<'de>,
     {
-        match self {
-            Value::Array(value) => visitor.visit_seq(ValueSeq::new(value)),
-            _ => ModelError::deserialization(self.to_string(), "Array").to_result(),
+        match self.value {
+            Value::Array(value) => visitor.visit_seq(ValueSeq::new(value, self.options)),
+            Value::Bytes(value) => {
+                let value = value.into_iter().map(Value::from).collect();
+                visitor.visit_seq(ValueSeq::new(value, self.options))
+            }
+            value => ModelError::deserialization(value.to_string(), "Array").to_result(),
         }
     }
 
@@ -216,9 +269,9 @@ impl<'de> serde::de::Deserializer<'de> for Value {
     where
         V: serde::de::Visitor<'de>,
     {
-        match self {
-            Value::Map(value) => visitor.visit_map(ValueMap::new(value)),
-            _ => ModelError::deserialization(self.to_string(), "Map").to_result(),
+        match self.value {
+            Value::Map(value) => visitor.visit_map(ValueMap::new(value, self.options)),
+            value => ModelError::deserialization(value.to_string(), "Map").to_result(),
         }
     }
 
@@ -236,14 +289,37 @@ impl<'de> serde::de::Deserializer<'de> for Value {
 
     fn deserialize_enum<V>(
         self,
-        _name: &'static str,
-        _variants: &'static [&'static str],
-        _visitor: V,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        unimplemented!()
+        let (variant, value) = match self.value {
+            Value::String(variant) => (variant, None),
+            Value::Map(map) if map.len() == 1 => {
+                let (variant, value) = map.into_iter().next().unwrap();
+                (variant, Some(value))
+            }
+            value => return ModelError::deserialization(value.to_string(), name).to_result(),
+        };
+
+        let variant = if self.options.case_insensitive_enums {
+            variants
+                .iter()
+                .find(|v| v.eq_ignore_ascii_case(&variant))
+                .map(|v| v.to_string())
+                .unwrap_or(variant)
+        } else {
+            variant
+        };
+
+        visitor.visit_enum(ValueEnum {
+            variant,
+            value,
+            options: self.options,
+        })
     }
 
     fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -263,12 +339,14 @@ impl<'de> serde::de::Deserializer<'de> for Value {
 
 struct ValueSeq {
     elements: Enumerate<IntoIter<Value>>,
+    options: DeserializeOptions,
 }
 
 impl ValueSeq {
-    pub fn new(elements: Vec<Value>) -> Self {
+    pub fn new(elements: Vec<Value>, options: DeserializeOptions) -> Self {
         Self {
             elements: elements.into_iter().enumerate(),
+            options,
         }
     }
 }
@@ -282,7 +360,9 @@ impl<'de> serde::de::SeqAccess<'de> for ValueSeq {
     {
         match self.elements.next() {
             None => Ok(None),
-            Some((_, value)) => seed.deserialize(value).map(Some),
+            Some((_, value)) => seed
+                .deserialize(ValueDeserializer::with_options(value, self.options))
+                .map(Some),
         }
     }
 
@@ -296,12 +376,14 @@ impl<'de> serde::de::SeqAccess<'de> for ValueSeq {
 
 struct ValueMap {
     elements: VecDeque<(String, Value)>,
+    options: DeserializeOptions,
 }
 
 impl ValueMap {
-    pub fn new(object: HashMap<String, Value>) -> Self {
+    pub fn new(object: HashMap<String, Value>, options: DeserializeOptions) -> Self {
         Self {
             elements: object.into_iter().collect(),
+            options,
         }
     }
 }
@@ -326,10 +408,150 @@ impl<'de> serde::de::MapAccess<'de> for ValueMap {
         V: serde::de::DeserializeSeed<'de>,
     {
         let (_, value) = self.elements.pop_front().unwrap();
-        serde::de::DeserializeSeed::deserialize(seed, value)
+        serde::de::DeserializeSeed::deserialize(
+            seed,
+            ValueDeserializer::with_options(value, self.options),
+        )
     }
 }
 
+struct ValueEnum {
+    variant: String,
+    value: Option<Value>,
+    options: DeserializeOptions,
+}
+
+impl<'de> serde::de::EnumAccess<'de> for ValueEnum {
+    type Error = ModelError;
+
+    type Variant = ValueVariant;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: serde::de::DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(Value::String(self.variant))?;
+        Ok((
+            variant,
+            ValueVariant {
+                value: self.value,
+                options: self.options,
+            },
+        ))
+    }
+}
+
+struct ValueVariant {
+    value: Option<Value>,
+    options: DeserializeOptions,
+}
+
+impl<'de> serde::de::VariantAccess<'de> for ValueVariant {
+    type Error = ModelError;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        match self.value {
+            None | Some(Value::None) => Ok(()),
+            Some(value) => {
+                ModelError::deserialization(value.to_string(), "unit variant").to_result()
+            }
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: serde::de::DeserializeSeed<'de>,
+    {
+        match self.value {
+            Some(value) => seed.deserialize(ValueDeserializer::with_options(value, self.options)),
+            None => {
+                ModelError::deserialization("unit variant".into(), "newtype variant").to_result()
+            }
+        }
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        match self.value {
+            Some(Value::Array(value)) => visitor.visit_seq(ValueSeq::new(value, self.options)),
+            Some(value) => {
+                ModelError::deserialization(value.to_string(), "tuple variant").to_result()
+            }
+            None => ModelError::deserialization("unit variant".into(), "tuple variant").to_result(),
+        }
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        match self.value {
+            Some(Value::Map(value)) => visitor.visit_map(ValueMap::new(value, self.options)),
+            Some(value) => {
+                ModelError::deserialization(value.to_string(), "struct variant").to_result()
+            }
+            None => {
+                ModelError::deserialization("unit variant".into(), "struct variant").to_result()
+            }
+        }
+    }
+}
+
+macro_rules! forward_to_value_deserializer {
+    ($($method:ident($($arg:ident: $ty:ty),*)),* $(,)?) => {
+        $(
+            fn $method<V>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: serde::de::Visitor<'de>,
+            {
+                ValueDeserializer::new(self).$method($($arg,)* visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> serde::de::Deserializer<'de> for Value {
+    type Error = ModelError;
+
+    forward_to_value_deserializer!(
+        deserialize_any(),
+        deserialize_bool(),
+        deserialize_i8(),
+        deserialize_i16(),
+        deserialize_i32(),
+        deserialize_i64(),
+        deserialize_u8(),
+        deserialize_u16(),
+        deserialize_u32(),
+        deserialize_u64(),
+        deserialize_f32(),
+        deserialize_f64(),
+        deserialize_char(),
+        deserialize_str(),
+        deserialize_string(),
+        deserialize_bytes(),
+        deserialize_byte_buf(),
+        deserialize_option(),
+        deserialize_unit(),
+        deserialize_unit_struct(name: &'static str),
+        deserialize_newtype_struct(name: &'static str),
+        deserialize_seq(),
+        deserialize_tuple(len: usize),
+        deserialize_tuple_struct(name: &'static str, len: usize),
+        deserialize_map(),
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]),
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]),
+        deserialize_identifier(),
+        deserialize_ignored_any(),
+    );
+}
+
 impl Value {
     pub fn deserialize<'de, T>(self) -> Result<T, ModelError>
     where
@@ -337,13 +559,23 @@ impl Value {
     {
         T::deserialize(self)
     }
+
+    /// Deserializes the value into `T`, using the specified options.
+    pub fn deserialize_with<'de, T>(self, options: DeserializeOptions) -> Result<T, ModelError>
+    where
+        T: serde::de::Deserialize<'de>,
+    {
+        T::deserialize(ValueDeserializer::with_options(self, options))
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::value_map;
+    use crate::{value_map, value_vec, Value};
 
-    use serde::Deserialize;
+    use serde::{Deserialize, Serialize};
+
+    use super::DeserializeOptions;
 
     #[derive(Deserialize)]
     struct Address {
@@ -375,4 +607,169 @@ mod tests {
         assert_eq!(person.address.street, "10 Downing Street");
         assert_eq!(person.address.city, "London");
     }
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    enum LogLevel {
+        Info,
+        Debug,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    enum Output {
+        Stdout,
+        File(String),
+        Syslog(String, u16),
+        Http { url: String, retries: u8 },
+    }
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    #[serde(tag = "type")]
+    enum Internal {
+        Memory { size: u32 },
+        Disk { path: String },
+    }
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    #[serde(tag = "type", content = "value")]
+    enum Adjacent {
+        Port(u16),
+        Name(String),
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Logging {
+        level: LogLevel,
+        outputs: Vec<Output>,
+    }
+
+    #[test]
+    fn test_deserialize_enum() {
+        let value = value_map! {
+            level: "Debug",
+            outputs: value_vec![
+                "Stdout",
+                value_map! { File: "app.log" },
+                value_map! { Syslog: value_vec!["localhost", 514] },
+                value_map! { Http: value_map! { url: "http://log", retries: 3 } },
+            ],
+        };
+
+        let logging = value.deserialize::<Logging>().unwrap();
+        assert_eq!(logging.level, LogLevel::Debug);
+        assert_eq!(
+            logging.outputs,
+            vec![
+                Output::Stdout,
+                Output::File("app.log".into()),
+                Output::Syslog("localhost".into(), 514),
+                Output::Http {
+                    url: "http://log".into(),
+                    retries: 3
+                },
+            ]
+        );
+
+        let internal = value_map! { type: "Disk", path: "/tmp" };
+        assert_eq!(
+            internal.deserialize::<Internal>().unwrap(),
+            Internal::Disk {
+                path: "/tmp".into()
+            }
+        );
+
+        let adjacent = value_map! { type: "Port", value: 80 };
+        assert_eq!(
+            adjacent.deserialize::<Adjacent>().unwrap(),
+            Adjacent::Port(80)
+        );
+    }
+
+    #[test]
+    fn test_deserialize_enum_errors() {
+        assert!(Value::from("Trace").deserialize::<LogLevel>().is_err());
+        assert!(Value::from("debug").deserialize::<LogLevel>().is_err());
+        assert!(Value::from(1).deserialize::<LogLevel>().is_err());
+        assert!(value_map! { File: 1, Stdout: 1 }
+            .deserialize::<Output>()
+            .is_err());
+        assert!(value_map! { Stdout: 1 }.deserialize::<Output>().is_err());
+        assert!(Value::from("File").deserialize::<Output>().is_err());
+        assert!(value_map! { Syslog: "x" }.deserialize::<Output>().is_err());
+    }
+
+    #[test]
+    fn test_deserialize_enum_case_insensitive() {
+        let value = value_map! {
+            level: "debug",
+            outputs: value_vec![value_map! { file: "app.log" }],
+        };
+
+        let options = DeserializeOptions::new().with_case_insensitive_enums(true);
+        let logging = value.deserialize_with::<Logging>(options).unwrap();
+
+        assert_eq!(logging.level, LogLevel::Debug);
+        assert_eq!(logging.outputs, vec![Output::File("app.log".into())]);
+    }
+
+    #[test]
+    fn test_deserialize_enum_round_trip() {
+        let values = vec![
+            Output::Stdout,
+            Output::File("app.log".into()),
+            Output::Syslog("localhost".into(), 514),
+            Output::Http {
+                url: "http://log".into(),
+                retries: 3,
+            },
+        ];
+
+        for output in values {
+            let value = Value::serialize(&output).unwrap();
+            assert_eq!(value.deserialize::<Output>().unwrap(), output);
+        }
+
+        let internal = Internal::Memory { size: 10 };
+        let value = Value::serialize(&internal).unwrap();
+        assert_eq!(value.deserialize::<Internal>().unwrap(), internal);
+
+        let adjacent = Adjacent::Name("x".into());
+        let value = Value::serialize(&adjacent).unwrap();
+        assert_eq!(value.deserialize::<Adjacent>().unwrap(), adjacent);
+    }
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Secrets {
+        #[serde(with = "serde_bytes")]
+        key: Vec<u8>,
+        #[serde(with = "serde_bytes")]
+        salt: Vec<u8>,
+        list: Vec<u8>,
+    }
+
+    #[test]
+    fn test_deserialize_bytes() {
+        let value = value_map! {
+            key: "AQI=",
+            salt: "hex:ff00",
+            list: Value::Bytes(vec![3, 4]),
+        };
+
+        let secrets = value.deserialize::<Secrets>().unwrap();
+        assert_eq!(secrets.key, vec![1, 2]);
+        assert_eq!(secrets.salt, vec![255, 0]);
+        assert_eq!(secrets.list, vec![3, 4]);
+
+        let value = value_map! { key: "0102", salt: "", list: value_vec![1] };
+        let options = DeserializeOptions::new().with_bytes_encoding(super::BytesEncoding::Hex);
+        let secrets = value.deserialize_with::<Secrets>(options).unwrap();
+        assert_eq!(secrets.key, vec![1, 2]);
+
+        let value = Value::serialize(&secrets).unwrap();
+        assert_eq!(value.get("key"), Value::Bytes(vec![1, 2]));
+        assert_eq!(value.deserialize::<Secrets>().unwrap(), secrets);
+
+        assert!(value_map! { key: "!", salt: "", list: Value::new_array() }
+            .deserialize::<Secrets>()
+            .is_err());
+    }
 }
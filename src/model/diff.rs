@@ -0,0 +1,215 @@
+use super::{MergeOptions, MergeStrategy, ModelError, Value, ValuePath};
+
+impl Value {
+    /// Returns the smallest map that, merged into `base` with the default `MergeOptions`,
+    /// results in this value. See `diff_with`.
+    pub fn diff(&self, base: &Value) -> Result<Value, ModelError> {
+        self.diff_with(base, &MergeOptions::default())
+    }
+
+    /// Returns the smallest map that, merged into `base` with `options`, results in this value.
+    ///
+    /// Keys equal to the ones in `base` are left out and keys missing from this value are set
+    /// to the unset sentinel. Both values must be maps.
+    ///
+    /// Fails if a key must be removed and there is no unset sentinel, or if an array can't be
+    /// produced by its merge strategy, like an `Append` array without the lower elements.
+    pub fn diff_with(&self, base: &Value, options: &MergeOptions) -> Result<Value, ModelError> {
+        match (self, base) {
+            (Value::Map(_), Value::Map(_)) => diff_at(self, base, options, &mut ValuePath::root()),
+            _ => Err(ModelError::OperationOnlyForMapValue),
+        }
+    }
+}
+
+fn diff_at(
+    value: &Value,
+    base: &Value,
+    options: &MergeOptions,
+    path: &mut ValuePath,
+) -> Result<Value, ModelError> {
+    let (map, base_map) = match (value, base) {
+        (Value::Map(map), Value::Map(base_map)) => (map, base_map),
+        _ => unreachable!("only maps are compared"),
+    };
+
+    let mut delta = Value::new_map();
+
+    for (key, value) in map {
+        if options.is_directive(key) {
+            continue;
+        }
+
+        path.push_key(key.as_str());
+        let key_delta = match base_map.get(key) {
+            None => Some(value.clone()),
+            Some(base) => diff_value(value, base, options, path)?,
+        };
+        path.pop();
+
+        if let Some(key_delta) = key_delta {
+            delta.set(key, key_delta)?;
+        }
+    }
+
+    for key in base_map.keys().filter(|key| !map.contains_key(*key)) {
+        match options.get_unset_sentinel() {
+            Some(sentinel) => {
+                delta.set(key, Value::from(sentinel))?;
+            }
+            None => {
+                path.push_key(key.as_str());
+                let error = diff_error(
+                    path,
+                    "the key must be removed, but there's no unset sentinel",
+                );
+                path.pop();
+                return Err(error);
+            }
+        }
+    }
+
+    Ok(delta)
+}
+
+/// Returns the value to merge into `base` to get `value`, `None` if they are equal.
+fn diff_value(
+    value: &Value,
+    base: &Value,
+    options: &MergeOptions,
+    path: &mut ValuePath,
+) -> Result<Option<Value>, ModelError> {
+    if same_value(value, base) {
+        return Ok(None);
+    }
+
+    match (value, base) {
+        (Value::Map(_), Value::Map(_)) => {
+            let delta = diff_at(value, base, options, path)?;
+            Ok(Some(delta))
+        }
+        (Value::Array(array), Value::Array(base)) => {
+            diff_array(array, base, options, path).map(Some)
+        }
+        _ => Ok(Some(value.clone())),
+    }
+}
+
+fn diff_array(
+    array: &[Value],
+    base: &[Value],
+    options: &MergeOptions,
+    path: &ValuePath,
+) -> Result<Value, ModelError> {
+    let delta = match options.strategy_for(path) {
+        MergeStrategy::Replace => Some(array.to_vec()),
+        MergeStrategy::Append => array.strip_prefix(base).map(|added| added.to_vec()),
+        MergeStrategy::Prepend => array.strip_suffix(base).map(|added| added.to_vec()),
+        MergeStrategy::Union => array.strip_prefix(base).and_then(|added| {
+            let unique = added
+                .iter()
+                .enumerate()
+                .all(|(i, v)| !base.contains(v) && !added[..i].contains(v));
+            unique.then(|| added.to_vec())
+        }),
+        MergeStrategy::MergeByKey(_) => None,
+    };
+
+    match delta {
+        Some(delta) => Ok(Value::Array(delta)),
+        None => Err(diff_error(
+            path,
+            "the array can't be produced by its merge strategy",
+        )),
+    }
+}
+
+/// Compares values like `==`, but integers of different types with the same value are equal.
+fn same_value(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Map(a), Value::Map(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(k, v)| b.get(k).is_some_and(|other| same_value(v, other)))
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same_value(a, b))
+        }
+        _ => match (as_integer(a), as_integer(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => a == b,
+        },
+    }
+}
+
+fn as_integer(value: &Value) -> Option<i128> {
+    match value {
+        Value::Integer(v) => Some(*v as i128),
+        Value::LongInteger(v) => Some(*v),
+        Value::UInteger(v) => Some(*v as i128),
+        Value::ULongInteger(v) => i128::try_from(*v).ok(),
+        _ => None,
+    }
+}
+
+fn diff_error(path: &ValuePath, why: &str) -> ModelError {
+    ModelError::DiffError {
+        path: path.to_string(),
+        why: why.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{value_map, value_vec};
+
+    use super::*;
+
+    #[test]
+    fn test_model_value_diff() {
+        let base = value_map! {
+            theme: "light",
+            font: value_map! { size: 10, family: "mono" },
+            plugins: value_vec!["git"],
+            port: Value::UInteger(80),
+        };
+        let value = value_map! {
+            theme: "dark",
+            font: value_map! { size: 10 },
+            plugins: value_vec!["git"],
+            port: 80,
+            tabs: 4,
+        };
+
+        let options = MergeOptions::default().with_unset_sentinel(Some("!unset".into()));
+        let delta = value.diff_with(&base, &options).unwrap();
+        assert_eq!(
+            delta,
+            value_map! {
+                theme: "dark",
+                font: value_map! { family: "!unset" },
+                tabs: 4,
+            }
+        );
+        assert!(same_value(&base.clone_merge_with(&delta, &options), &value));
+        assert!(value.diff(&base).is_err());
+    }
+
+    #[test]
+    fn test_model_value_diff_with_strategy() {
+        let options = MergeOptions::new(MergeStrategy::Append);
+        let base = value_map! { hosts: value_vec!["a"] };
+
+        let value = value_map! { hosts: value_vec!["a", "b"] };
+        let delta = value.diff_with(&base, &options).unwrap();
+        assert_eq!(delta, value_map! { hosts: value_vec!["b"] });
+
+        let value = value_map! { hosts: value_vec!["b"] };
+        let err = value.diff_with(&base, &options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Can't compute the difference at path 'hosts': the array can't be produced by its merge strategy"
+        );
+        assert!(Value::new_map().diff_with(&base, &options).is_err());
+    }
+}
@@ -16,6 +16,18 @@ pub enum ModelError {
         to: &'static str,
         why: &'static str,
     },
+    #[error("Invalid path '{path}': {why}")]
+    InvalidPath { path: String, why: String },
+    #[error("Type mismatch at path '{path}': expected {expected}, found {found}")]
+    PathTypeMismatch {
+        path: String,
+        expected: &'static str,
+        found: &'static str,
+    },
+    #[error("Value doesn't match schema: {}", super::schema::join_errors(.0))]
+    SchemaValidation(Vec<super::SchemaError>),
+    #[error("Can't compute the difference at path '{path}': {why}")]
+    DiffError { path: String, why: String },
 }
 
 impl serde::de::Error for ModelError {
@@ -65,6 +77,23 @@ impl PartialEq for ModelError {
                     why: w2,
                 },
             ) => f1 == f2 && t1 == t2 && w1 == w2,
+            (
+                ModelError::InvalidPath { path: p1, why: w1 },
+                ModelError::InvalidPath { path: p2, why: w2 },
+            ) => p1 == p2 && w1 == w2,
+            (
+                ModelError::PathTypeMismatch {
+                    path: p1,
+                    expected: e1,
+                    found: f1,
+                },
+                ModelError::PathTypeMismatch {
+                    path: p2,
+                    expected: e2,
+                    found: f2,
+                },
+            ) => p1 == p2 && e1 == e2 && f1 == f2,
+            (ModelError::SchemaValidation(e1), ModelError::SchemaValidation(e2)) => e1 == e2,
             _ => false,
         }
     }
@@ -21,6 +21,7 @@ impl TryInto<bool> for Value {
             Value::None => err_conversion_error!(None, bool),
             Value::Array(_) => err_conversion_error!(Array, bool),
             Value::Map(_) => err_conversion_error!(Map, bool),
+            Value::Bytes(_) => err_conversion_error!(Bytes, bool),
         }
     }
 }
@@ -53,6 +54,7 @@ impl TryInto<i8> for Value {
             Value::None => err_conversion_error!(None, i8),
             Value::Array(_) => err_conversion_error!(Array, i8),
             Value::Map(_) => err_conversion_error!(Map, i8),
+            Value::Bytes(_) => err_conversion_error!(Bytes, i8),
         }
     }
 }
@@ -78,6 +80,7 @@ impl TryInto<i16> for Value {
             Value::None => err_conversion_error!(None, i16),
             Value::Array(_) => err_conversion_error!(Array, i16),
             Value::Map(_) => err_conversion_error!(Map, i16),
+            Value::Bytes(_) => err_conversion_error!(Bytes, i16),
         }
     }
 }
@@ -103,6 +106,7 @@ impl TryInto<i32> for Value {
             Value::None => err_conversion_error!(None, i32),
             Value::Array(_) => err_conversion_error!(Array, i32),
             Value::Map(_) => err_conversion_error!(Map, i32),
+            Value::Bytes(_) => err_conversion_error!(Bytes, i32),
         }
     }
 }
@@ -125,6 +129,7 @@ impl TryInto<i64> for Value {
             Value::None => err_conversion_error!(None, i64),
             Value::Array(_) => err_conversion_error!(Array, i64),
             Value::Map(_) => err_conversion_error!(Map, i64),
+            Value::Bytes(_) => err_conversion_error!(Bytes, i64),
         }
     }
 }
@@ -147,6 +152,7 @@ impl TryInto<i128> for Value {
             Value::None => err_conversion_error!(None, i128),
             Value::Array(_) => err_conversion_error!(Array, i128),
             Value::Map(_) => err_conversion_error!(Map, i128),
+            Value::Bytes(_) => err_conversion_error!(Bytes, i128),
         }
     }
 }
@@ -172,6 +178,7 @@ impl TryInto<u8> for Value {
             Value::None => err_conversion_error!(None, u8),
             Value::Array(_) => err_conversion_error!(Array, u8),
             Value::Map(_) => err_conversion_error!(Map, u8),
+            Value::Bytes(_) => err_conversion_error!(Bytes, u8),
         }
     }
 }
@@ -197,6 +204,7 @@ impl TryInto<u16> for Value {
             Value::None => err_conversion_error!(None, u16),
             Value::Array(_) => err_conversion_error!(Array, u16),
             Value::Map(_) => err_conversion_error!(Map, u16),
+            Value::Bytes(_) => err_conversion_error!(Bytes, u16),
         }
     }
 }
@@ -222,6 +230,7 @@ impl TryInto<u32> for Value {
             Value::None => err_conversion_error!(None, u32),
             Value::Array(_) => err_conversion_error!(Array, u32),
             Value::Map(_) => err_conversion_error!(Map, u32),
+            Value::Bytes(_) => err_conversion_error!(Bytes, u32),
         }
     }
 }
@@ -244,6 +253,7 @@ impl TryInto<u64> for Value {
             Value::None => err_conversion_error!(None, u64),
             Value::Array(_) => err_conversion_error!(Array, u64),
             Value::Map(_) => err_conversion_error!(Map, u64),
+            Value::Bytes(_) => err_conversion_error!(Bytes, u64),
         }
     }
 }
@@ -269,6 +279,7 @@ impl TryInto<u128> for Value {
             Value::None => err_conversion_error!(None, u128),
             Value::Array(_) => err_conversion_error!(Array, u128),
             Value::Map(_) => err_conversion_error!(Map, u128),
+            Value::Bytes(_) => err_conversion_error!(Bytes, u128),
         }
     }
 }
@@ -291,6 +302,7 @@ impl TryInto<f64> for Value {
             Value::None => err_conversion_error!(None, f64),
             Value::Array(_) => err_conversion_error!(Array, f64),
             Value::Map(_) => err_conversion_error!(Map, f64),
+            Value::Bytes(_) => err_conversion_error!(Bytes, f64),
         }
     }
 }
@@ -325,6 +337,7 @@ impl TryInto<char> for Value {
             Value::Float(_) => err_conversion_error!(Float, char),
             Value::Array(_) => err_conversion_error!(Array, char),
             Value::Map(_) => err_conversion_error!(Map, char),
+            Value::Bytes(_) => err_conversion_error!(Bytes, char),
         }
     }
 }
@@ -344,6 +357,7 @@ impl TryInto<String> for Value {
             Value::None => err_conversion_error!(None, String),
             Value::Array(_) => err_conversion_error!(Array, String),
             Value::Map(_) => err_conversion_error!(Map, String),
+            Value::Bytes(value) => Ok(super::bytes::encode_base64(&value)),
         }
     }
 }
@@ -1,38 +1,330 @@
-use super::Value;
+use std::collections::HashMap;
+
+use super::{IntoValuePath, ModelError, PathSegment, Value, ValuePath};
+
+/// Defines how an array is merged with the array of a higher layer.
+///
+/// Maps are always merged recursively and other values are always replaced.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum MergeStrategy {
+    /// Higher layer array replaces the lower one
+    #[default]
+    Replace,
+    /// Higher layer elements are added after the lower ones
+    Append,
+    /// Higher layer elements are added before the lower ones
+    Prepend,
+    /// Higher layer elements are appended if not equal to any lower element
+    Union,
+    /// Map elements with the same value in the identifier field are merged,
+    /// other elements are appended
+    MergeByKey(String),
+}
+
+/// Options used by `Value::merge_with`.
+///
+/// Holds a default `MergeStrategy` and a list of strategies for specific paths.
+/// Paths are matched against map keys only, array indexes are ignored, so a
+/// strategy for `servers.tags` applies to the `tags` of every element of `servers`.
+///
+/// It also defines how a higher layer can remove values set by a lower layer:
+/// - unset sentinel: a string value, disabled by default, that removes the key
+///   it's assigned to, e.g. `level = "!unset"`
+/// - unset directive: a map key, disabled by default, that holds a path or a list of
+///   paths to remove, relative to the map it's defined in, e.g. `unset = ["log.level"]`
+///
+/// Removed keys are missing from the merged value, unlike keys set to `Value::None`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeOptions {
+    strategy: MergeStrategy,
+    path_strategies: Vec<(ValuePath, MergeStrategy)>,
+    unset_sentinel: Option<String>,
+    unset_directive: Option<String>,
+}
+
+impl Default for MergeOptions {
+    fn default() -> Self {
+        Self::new(MergeStrategy::default())
+    }
+}
+
+impl MergeOptions {
+    pub fn new(strategy: MergeStrategy) -> Self {
+        Self {
+            strategy,
+            path_strategies: Vec::new(),
+            unset_sentinel: None,
+            unset_directive: None,
+        }
+    }
+
+    pub fn get_strategy(&self) -> &MergeStrategy {
+        &self.strategy
+    }
+
+    pub fn set_strategy(&mut self, strategy: MergeStrategy) {
+        self.strategy = strategy;
+    }
+
+    /// Overrides the strategy used for the array at `path`.
+    ///
+    /// If the path already has a strategy, it's replaced.
+    pub fn set_path_strategy<P: IntoValuePath>(
+        &mut self,
+        path: P,
+        strategy: MergeStrategy,
+    ) -> Result<(), ModelError> {
+        let path = ValuePath::from(
+            path.into_value_path()?
+                .segments()
+                .iter()
+                .filter(|s| matches!(s, PathSegment::Key(_)))
+                .cloned()
+                .collect::<Vec<_>>(),
+        );
+
+        self.path_strategies.retain(|(p, _)| p != &path);
+        self.path_strategies.push((path, strategy));
+        Ok(())
+    }
+
+    /// Like `set_path_strategy`, but returns `self` for method chaining.
+    pub fn with_path_strategy<P: IntoValuePath>(
+        mut self,
+        path: P,
+        strategy: MergeStrategy,
+    ) -> Result<Self, ModelError> {
+        self.set_path_strategy(path, strategy)?;
+        Ok(self)
+    }
+
+    pub fn get_unset_sentinel(&self) -> Option<&str> {
+        self.unset_sentinel.as_deref()
+    }
+
+    /// Sets the string value that removes a key, `None` disables it.
+    pub fn set_unset_sentinel(&mut self, sentinel: Option<String>) {
+        self.unset_sentinel = sentinel;
+    }
+
+    /// Like `set_unset_sentinel`, but returns `self` for method chaining.
+    pub fn with_unset_sentinel(mut self, sentinel: Option<String>) -> Self {
+        self.set_unset_sentinel(sentinel);
+        self
+    }
+
+    pub fn get_unset_directive(&self) -> Option<&str> {
+        self.unset_directive.as_deref()
+    }
+
+    /// Sets the map key that lists paths to remove, `None` disables it.
+    pub fn set_unset_directive(&mut self, directive: Option<String>) {
+        self.unset_directive = directive;
+    }
+
+    /// Like `set_unset_directive`, but returns `self` for method chaining.
+    pub fn with_unset_directive(mut self, directive: Option<String>) -> Self {
+        self.set_unset_directive(directive);
+        self
+    }
+
+    /// Returns true if `value` is the unset sentinel.
+    pub fn is_unset(&self, value: &Value) -> bool {
+        match (value, &self.unset_sentinel) {
+            (Value::String(value), Some(sentinel)) => value == sentinel,
+            _ => false,
+        }
+    }
+
+    pub(crate) fn is_directive(&self, key: &str) -> bool {
+        self.unset_directive.as_deref() == Some(key)
+    }
+
+    /// Paths listed in the unset directive of `map`.
+    pub(crate) fn unset_paths(&self, map: &HashMap<String, Value>) -> Vec<ValuePath> {
+        let paths = match self.unset_directive.as_ref().and_then(|d| map.get(d)) {
+            Some(Value::Array(paths)) => paths.iter().collect(),
+            Some(path) => vec![path],
+            None => return Vec::new(),
+        };
+
+        paths
+            .into_iter()
+            .filter_map(|path| match path {
+                Value::String(path) => match ValuePath::parse(path) {
+                    Ok(path) => Some(path),
+                    Err(e) => {
+                        log::warn!("Ignoring invalid unset path: {}", e);
+                        None
+                    }
+                },
+                other => {
+                    log::warn!("Ignoring unset path that is not a string: {}", other);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Clones `value` without unset sentinels and directives.
+    pub fn strip_unset(&self, value: &Value) -> Value {
+        match value {
+            Value::Map(map) => Value::Map(
+                map.iter()
+                    .filter(|(k, v)| !self.is_directive(k) && !self.is_unset(v))
+                    .map(|(k, v)| (k.clone(), self.strip_unset(v)))
+                    .collect(),
+            ),
+            Value::Array(array) => Value::Array(
+                array
+                    .iter()
+                    .filter(|v| !self.is_unset(v))
+                    .map(|v| self.strip_unset(v))
+                    .collect(),
+            ),
+            _ => value.clone(),
+        }
+    }
+
+    /// Returns the strategy to use for the array at `path`.
+    pub fn strategy_for(&self, path: &ValuePath) -> &MergeStrategy {
+        self.path_strategies
+            .iter()
+            .find(|(p, _)| p == path)
+            .map(|(_, s)| s)
+            .unwrap_or(&self.strategy)
+    }
+}
 
 impl Value {
+    /// Merges `other` into this value, using the default `MergeStrategy::Replace`.
+    ///
+    /// Only has effect when both values are maps or arrays.
     pub fn merge(&mut self, other: &Value) {
+        self.merge_with(other, &MergeOptions::default());
+    }
+
+    /// Merges `other` into this value, using `options` to decide how arrays are merged
+    /// and which values are removed.
+    ///
+    /// Only has effect when both values are maps or arrays.
+    pub fn merge_with(&mut self, other: &Value, options: &MergeOptions) {
+        match (&*self, other) {
+            (Value::Map(_), Value::Map(_)) | (Value::Array(_), Value::Array(_)) => {
+                self.merge_at(other, options, &mut ValuePath::root());
+            }
+            _ => {}
+        }
+    }
+
+    pub fn clone_merge(&self, other: &Value) -> Value {
+        let mut value = self.clone();
+        value.merge(other);
+        value
+    }
+
+    pub fn clone_merge_with(&self, other: &Value, options: &MergeOptions) -> Value {
+        let mut value = self.clone();
+        value.merge_with(other, options);
+        value
+    }
+
+    fn merge_at(&mut self, other: &Value, options: &MergeOptions, path: &mut ValuePath) {
         match (self, other) {
             (Value::Map(lhs), Value::Map(rhs)) => {
+                for unset_path in options.unset_paths(rhs) {
+                    remove_from_map(lhs, &unset_path);
+                }
+
                 for (key, value) in rhs {
+                    if options.is_directive(key) {
+                        continue;
+                    }
+
+                    if options.is_unset(value) {
+                        lhs.remove(key);
+                        continue;
+                    }
+
                     match lhs.get_mut(key) {
                         Some(lhs_value) => {
-                            if lhs_value.is_map() && value.is_map() {
-                                lhs_value.merge(value);
-                            } else {
-                                lhs.insert(key.clone(), value.clone());
-                            }
+                            path.push_key(key.as_str());
+                            lhs_value.merge_at(value, options, path);
+                            path.pop();
                         }
                         None => {
-                            lhs.insert(key.clone(), value.clone());
+                            lhs.insert(key.clone(), options.strip_unset(value));
                         }
                     }
                 }
-            },
-            _ => {}
+            }
+            (Value::Array(lhs), Value::Array(rhs)) => {
+                merge_arrays(lhs, rhs, options, path);
+            }
+            (lhs, rhs) => *lhs = options.strip_unset(rhs),
         }
     }
+}
 
-    pub fn clone_merge(&self, other: &Value) -> Value {
-        let mut value = self.clone();
-        value.merge(other);
-        value
+fn remove_from_map(map: &mut HashMap<String, Value>, path: &ValuePath) {
+    let (first, rest) = match path.segments().split_first() {
+        Some((PathSegment::Key(first), rest)) => (first, rest),
+        _ => return,
+    };
+
+    if rest.is_empty() {
+        map.remove(first);
+    } else if let Some(value) = map.get_mut(first) {
+        if let Err(e) = value.remove_path(ValuePath::from(rest.to_vec())) {
+            log::warn!("Can't unset path '{}': {}", path, e);
+        }
+    }
+}
+
+fn merge_arrays(lhs: &mut Vec<Value>, rhs: &[Value], options: &MergeOptions, path: &mut ValuePath) {
+    let values = rhs.iter().filter(|v| !options.is_unset(v));
+
+    match options.strategy_for(path) {
+        MergeStrategy::Replace => *lhs = values.map(|v| options.strip_unset(v)).collect(),
+        MergeStrategy::Append => lhs.extend(values.map(|v| options.strip_unset(v))),
+        MergeStrategy::Prepend => {
+            let mut values: Vec<Value> = values.map(|v| options.strip_unset(v)).collect();
+            values.append(lhs);
+            *lhs = values;
+        }
+        MergeStrategy::Union => {
+            for value in values.map(|v| options.strip_unset(v)) {
+                if !lhs.contains(&value) {
+                    lhs.push(value);
+                }
+            }
+        }
+        MergeStrategy::MergeByKey(field) => {
+            for value in values {
+                let id = match value {
+                    Value::Map(map) => map.get(field),
+                    _ => None,
+                };
+
+                let existing = id.and_then(|id| {
+                    lhs.iter_mut()
+                        .find(|v| matches!(v, Value::Map(map) if map.get(field) == Some(id)))
+                });
+
+                match existing {
+                    Some(existing) => existing.merge_at(value, options, path),
+                    None => lhs.push(options.strip_unset(value)),
+                }
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::value_map;
+    use crate::{value_map, value_vec};
+
+    use super::*;
 
     #[test]
     fn test_merge() {
@@ -117,4 +409,118 @@ mod tests {
         assert_eq!(value.get("education").get("high_school").try_into_string().unwrap(), "Anytown High School");
         assert_eq!(value.get("education").get("college").try_into_string().unwrap(), "Anytown College");
     }
-}
\ No newline at end of file
+
+    fn plugins() -> (crate::Value, crate::Value) {
+        let lower = value_map! {
+            plugins: value_vec!["a", "b"],
+            servers: value_vec![
+                value_map! { name: "api", port: 80, tags: value_vec!["x"] },
+                value_map! { name: "db", port: 5432 },
+            ],
+        };
+
+        let higher = value_map! {
+            plugins: value_vec!["b", "c"],
+            servers: value_vec![
+                value_map! { name: "api", port: 8080, tags: value_vec!["y"] },
+                value_map! { name: "cache", port: 6379 },
+                value_map! { port: 1 },
+            ],
+        };
+
+        (lower, higher)
+    }
+
+    #[test]
+    fn test_merge_array_strategies() {
+        let (lower, higher) = plugins();
+
+        let cases = [
+            (MergeStrategy::Replace, value_vec!["b", "c"]),
+            (MergeStrategy::Append, value_vec!["a", "b", "b", "c"]),
+            (MergeStrategy::Prepend, value_vec!["b", "c", "a", "b"]),
+            (MergeStrategy::Union, value_vec!["a", "b", "c"]),
+        ];
+
+        for (strategy, expected) in cases {
+            let value = lower.clone_merge_with(&higher, &MergeOptions::new(strategy.clone()));
+            assert_eq!(value.get("plugins"), expected, "strategy {:?}", strategy);
+        }
+    }
+
+    #[test]
+    fn test_merge_by_key() {
+        let (lower, higher) = plugins();
+
+        let options = MergeOptions::default()
+            .with_path_strategy("servers", MergeStrategy::MergeByKey("name".into()))
+            .unwrap()
+            .with_path_strategy("servers[0].tags", MergeStrategy::Append)
+            .unwrap();
+
+        let value = lower.clone_merge_with(&higher, &options);
+        let servers = value.get("servers");
+
+        assert_eq!(value.get("plugins"), value_vec!["b", "c"]);
+        assert_eq!(servers.len(), 4);
+        assert_eq!(servers.at(0).get("port").try_into_i64().unwrap(), 8080);
+        assert_eq!(servers.at(0).get("tags"), value_vec!["x", "y"]);
+        assert_eq!(servers.at(1).get("name").try_into_string().unwrap(), "db");
+        assert_eq!(servers.at(2).get("name").try_into_string().unwrap(), "cache");
+        assert_eq!(servers.at(3).get("port").try_into_i64().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_merge_unset_sentinel() {
+        let mut value = value_map! {
+            log: value_map! { level: "info", file: "app.log" },
+            plugins: value_vec!["a", "b"],
+            port: 80,
+        };
+
+        let local = value_map! {
+            log: value_map! { level: "!unset" },
+            plugins: value_vec!["c", "!unset"],
+            port: Value::None,
+            extra: value_map! { key: "!unset", other: 1 },
+        };
+
+        let options = MergeOptions::default().with_unset_sentinel(Some("!unset".into()));
+        value.merge_with(&local, &options);
+
+        assert!(!value.contains_path("log.level"));
+        assert!(value.contains_path("log.file"));
+        assert_eq!(value.get("plugins"), value_vec!["c"]);
+        assert!(value.contains_path("port"));
+        assert!(value.get("port").is_none());
+        assert_eq!(value.get("extra"), value_map! { other: 1 });
+
+        let mut value = value_map! { level: "info" };
+        value.merge(&value_map! { level: "!unset" });
+        assert_eq!(value.get("level").try_into_string().unwrap(), "!unset");
+    }
+
+    #[test]
+    fn test_merge_unset_directive() {
+        let mut value = value_map! {
+            log: value_map! {
+                level: "info",
+                file: value_map! { path: "app.log", size: 10 },
+            },
+            port: 80,
+        };
+
+        let local = value_map! {
+            unset: value_vec!["port", "log.file.size"],
+            log: value_map! { unset: "level" },
+        };
+
+        let options = MergeOptions::default().with_unset_directive(Some("unset".into()));
+        value.merge_with(&local, &options);
+
+        assert_eq!(
+            value,
+            value_map! { log: value_map! { file: value_map! { path: "app.log" } } }
+        );
+    }
+}
@@ -1,16 +1,25 @@
 #[macro_use]
 pub mod errors;
 
+pub mod bytes;
 pub mod de;
+pub mod diff;
 pub mod from;
 pub mod into;
 pub mod iter;
 pub mod merge;
 pub mod new;
+pub mod path;
+pub mod schema;
 pub mod ser;
 pub mod value;
 pub mod visitor;
 
+pub use bytes::BytesEncoding;
+pub use de::{DeserializeOptions, ValueDeserializer};
 pub use errors::ModelError;
 pub use iter::ValueIter;
+pub use merge::{MergeOptions, MergeStrategy};
+pub use path::{IntoValuePath, PathSegment, ValuePath, MAX_ARRAY_PADDING};
+pub use schema::{Schema, SchemaError, ValueType};
 pub use value::Value;
@@ -0,0 +1,656 @@
+//! Path expressions used to navigate nested `Value`s.
+//!
+//! A path is a list of segments separated by dots, like `servers[2].host`:
+//! - `key`: a map key, can't contain `.`, `[`, `]` or `"`
+//! - `[2]`: an array index
+//! - `"a.b"` or `["a.b"]`: a quoted map key, can contain any character, `\` escapes the next one
+//!
+//! An empty path points to the value itself.
+
+use std::{fmt::Display, str::FromStr};
+
+use super::{ModelError, Value};
+
+/// A single step in a `ValuePath`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    /// Key of a map value
+    Key(String),
+    /// Index of an array value
+    Index(usize),
+}
+
+impl PathSegment {
+    /// Name of the value type this segment can be applied to.
+    pub fn expected_type(&self) -> &'static str {
+        match self {
+            PathSegment::Key(_) => "Map",
+            PathSegment::Index(_) => "Array",
+        }
+    }
+
+    fn is_bare_key(key: &str) -> bool {
+        !key.is_empty() && !key.contains(['.', '[', ']', '"', '\\'])
+    }
+}
+
+/// A parsed path to a value nested in maps and arrays.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ValuePath {
+    segments: Vec<PathSegment>,
+}
+
+impl ValuePath {
+    /// Path that points to the value itself.
+    pub fn root() -> Self {
+        Self::default()
+    }
+
+    /// Parses a path expression, like `servers[2].host` or `log."file.path"`.
+    pub fn parse(path: &str) -> Result<Self, ModelError> {
+        PathParser::new(path).parse()
+    }
+
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.segments
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    pub fn last(&self) -> Option<&PathSegment> {
+        self.segments.last()
+    }
+
+    /// Path of the value that contains this one, `None` for the root path.
+    pub fn parent(&self) -> Option<ValuePath> {
+        if self.is_root() {
+            return None;
+        }
+
+        Some(Self {
+            segments: self.segments[..self.segments.len() - 1].to_vec(),
+        })
+    }
+
+    /// Returns true if `other` is this path or one of its ancestors.
+    pub fn starts_with(&self, other: &ValuePath) -> bool {
+        self.segments.starts_with(&other.segments)
+    }
+
+    /// Returns a new path with the segments of `other` appended to this one.
+    pub fn join(&self, other: &ValuePath) -> ValuePath {
+        let mut segments = self.segments.clone();
+        segments.extend_from_slice(&other.segments);
+        Self { segments }
+    }
+
+    pub fn push_key<K: Into<String>>(&mut self, key: K) {
+        self.segments.push(PathSegment::Key(key.into()));
+    }
+
+    pub fn push_index(&mut self, index: usize) {
+        self.segments.push(PathSegment::Index(index));
+    }
+
+    pub fn pop(&mut self) -> Option<PathSegment> {
+        self.segments.pop()
+    }
+
+    /// Like `push_key`, but returns `self` for method chaining.
+    pub fn with_key<K: Into<String>>(mut self, key: K) -> Self {
+        self.push_key(key);
+        self
+    }
+
+    /// Like `push_index`, but returns `self` for method chaining.
+    pub fn with_index(mut self, index: usize) -> Self {
+        self.push_index(index);
+        self
+    }
+
+    fn prefix(&self, len: usize) -> ValuePath {
+        Self {
+            segments: self.segments[..len].to_vec(),
+        }
+    }
+}
+
+impl From<Vec<PathSegment>> for ValuePath {
+    fn from(segments: Vec<PathSegment>) -> Self {
+        Self { segments }
+    }
+}
+
+impl FromStr for ValuePath {
+    type Err = ModelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl Display for ValuePath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, segment) in self.segments.iter().enumerate() {
+            match segment {
+                PathSegment::Index(index) => write!(f, "[{}]", index)?,
+                PathSegment::Key(key) => {
+                    if i > 0 {
+                        write!(f, ".")?;
+                    }
+
+                    if PathSegment::is_bare_key(key) {
+                        write!(f, "{}", key)?;
+                    } else {
+                        let escaped = key.replace('\\', "\\\\").replace('"', "\\\"");
+                        write!(f, "\"{}\"", escaped)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Conversion into a `ValuePath`, allows path methods to receive strings or parsed paths.
+pub trait IntoValuePath {
+    fn into_value_path(self) -> Result<ValuePath, ModelError>;
+}
+
+impl IntoValuePath for &str {
+    fn into_value_path(self) -> Result<ValuePath, ModelError> {
+        ValuePath::parse(self)
+    }
+}
+
+impl IntoValuePath for &String {
+    fn into_value_path(self) -> Result<ValuePath, ModelError> {
+        ValuePath::parse(self)
+    }
+}
+
+impl IntoValuePath for String {
+    fn into_value_path(self) -> Result<ValuePath, ModelError> {
+        ValuePath::parse(&self)
+    }
+}
+
+impl IntoValuePath for ValuePath {
+    fn into_value_path(self) -> Result<ValuePath, ModelError> {
+        Ok(self)
+    }
+}
+
+impl IntoValuePath for &ValuePath {
+    fn into_value_path(self) -> Result<ValuePath, ModelError> {
+        Ok(self.clone())
+    }
+}
+
+struct PathParser<'a> {
+    path: &'a str,
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    segments: Vec<PathSegment>,
+}
+
+impl<'a> PathParser<'a> {
+    fn new(path: &'a str) -> Self {
+        Self {
+            path,
+            chars: path.chars().peekable(),
+            segments: Vec::new(),
+        }
+    }
+
+    fn error(&self, why: &str) -> ModelError {
+        ModelError::InvalidPath {
+            path: self.path.to_string(),
+            why: why.to_string(),
+        }
+    }
+
+    fn parse(mut self) -> Result<ValuePath, ModelError> {
+        if self.path.is_empty() {
+            return Ok(ValuePath::root());
+        }
+
+        loop {
+            match self.chars.peek() {
+                None => return Err(self.error("expected a key after '.'")),
+                Some('"') => {
+                    let key = self.parse_quoted()?;
+                    self.segments.push(PathSegment::Key(key));
+                }
+                Some('[') => self.parse_bracket()?,
+                Some(_) => self.parse_bare()?,
+            }
+
+            while self.chars.peek() == Some(&'[') {
+                self.parse_bracket()?;
+            }
+
+            match self.chars.next() {
+                None => break,
+                Some('.') => continue,
+                Some(c) => return Err(self.error(&format!("unexpected character '{}'", c))),
+            }
+        }
+
+        Ok(ValuePath::from(self.segments))
+    }
+
+    fn parse_bare(&mut self) -> Result<(), ModelError> {
+        let mut key = String::new();
+
+        while let Some(&c) = self.chars.peek() {
+            match c {
+                '.' | '[' => break,
+                ']' | '"' | '\\' => {
+                    return Err(self.error(&format!("unexpected character '{}' in key", c)))
+                }
+                _ => {
+                    key.push(c);
+                    self.chars.next();
+                }
+            }
+        }
+
+        if key.is_empty() {
+            return Err(self.error("empty key"));
+        }
+
+        self.segments.push(PathSegment::Key(key));
+        Ok(())
+    }
+
+    fn parse_quoted(&mut self) -> Result<String, ModelError> {
+        self.chars.next(); // opening quote
+        let mut key = String::new();
+
+        loop {
+            match self.chars.next() {
+                None => return Err(self.error("unterminated quoted key")),
+                Some('"') => return Ok(key),
+                Some('\\') => match self.chars.next() {
+                    Some(c) => key.push(c),
+                    None => return Err(self.error("unterminated quoted key")),
+                },
+                Some(c) => key.push(c),
+            }
+        }
+    }
+
+    fn parse_bracket(&mut self) -> Result<(), ModelError> {
+        self.chars.next(); // opening bracket
+
+        if self.chars.peek() == Some(&'"') {
+            let key = self.parse_quoted()?;
+            if self.chars.next() != Some(']') {
+                return Err(self.error("expected ']' after quoted key"));
+            }
+            self.segments.push(PathSegment::Key(key));
+            return Ok(());
+        }
+
+        let mut digits = String::new();
+        loop {
+            match self.chars.next() {
+                None => return Err(self.error("unterminated index")),
+                Some(']') => break,
+                Some(c) => digits.push(c),
+            }
+        }
+
+        let index = digits
+            .trim()
+            .parse::<usize>()
+            .map_err(|_| self.error(&format!("invalid array index '{}'", digits)))?;
+
+        self.segments.push(PathSegment::Index(index));
+        Ok(())
+    }
+}
+
+/// Maximum number of `Value::None` elements `Value::set_path` adds before an index.
+pub const MAX_ARRAY_PADDING: usize = 1024;
+
+fn type_mismatch(path: &ValuePath, depth: usize, value: &Value) -> ModelError {
+    ModelError::PathTypeMismatch {
+        path: path.prefix(depth).to_string(),
+        expected: path.segments[depth].expected_type(),
+        found: value.type_name(),
+    }
+}
+
+impl Value {
+    /// Returns a reference to the value at `path`.
+    ///
+    /// Returns `Ok(None)` when the path does not exist, and an error if the path is invalid
+    /// or tries to index a value with the wrong type (e.g. a key in an array).
+    pub fn get_path<P: IntoValuePath>(&self, path: P) -> Result<Option<&Value>, ModelError> {
+        let path = path.into_value_path()?;
+        let mut current = self;
+
+        for (depth, segment) in path.segments().iter().enumerate() {
+            current = match (segment, current) {
+                (PathSegment::Key(key), Value::Map(map)) => match map.get(key) {
+                    Some(value) => value,
+                    None => return Ok(None),
+                },
+                (PathSegment::Index(index), Value::Array(array)) => match array.get(*index) {
+                    Some(value) => value,
+                    None => return Ok(None),
+                },
+                (_, Value::None) => return Ok(None),
+                (_, value) => return Err(type_mismatch(&path, depth, value)),
+            };
+        }
+
+        Ok(Some(current))
+    }
+
+    /// Returns a mutable reference to the value at `path`.
+    ///
+    /// Same rules as `get_path`.
+    pub fn get_path_mut<P: IntoValuePath>(
+        &mut self,
+        path: P,
+    ) -> Result<Option<&mut Value>, ModelError> {
+        let path = path.into_value_path()?;
+        let mut current = self;
+
+        for (depth, segment) in path.segments().iter().enumerate() {
+            current = match (segment, current) {
+                (PathSegment::Key(key), Value::Map(map)) => match map.get_mut(key) {
+                    Some(value) => value,
+                    None => return Ok(None),
+                },
+                (PathSegment::Index(index), Value::Array(array)) => match array.get_mut(*index) {
+                    Some(value) => value,
+                    None => return Ok(None),
+                },
+                (_, Value::None) => return Ok(None),
+                (_, value) => return Err(type_mismatch(&path, depth, value)),
+            };
+        }
+
+        Ok(Some(current))
+    }
+
+    /// Sets the value at `path`, creating any missing maps and arrays along the way.
+    ///
+    /// Arrays are padded with `Value::None` when the index is past its end, by up to
+    /// `MAX_ARRAY_PADDING` elements; indexes further away are rejected.
+    /// `Value::None` values in the path are replaced by the required map or array.
+    pub fn set_path<P: IntoValuePath>(
+        &mut self,
+        path: P,
+        value: Value,
+    ) -> Result<&mut Self, ModelError> {
+        let path = path.into_value_path()?;
+        let mut current = &mut *self;
+
+        for (depth, segment) in path.segments().iter().enumerate() {
+            if current.is_none() {
+                *current = match segment {
+                    PathSegment::Key(_) => Value::new_map(),
+                    PathSegment::Index(_) => Value::new_array(),
+                };
+            }
+
+            current = match (segment, current) {
+                (PathSegment::Key(key), Value::Map(map)) => {
+                    map.entry(key.clone()).or_insert(Value::None)
+                }
+                (PathSegment::Index(index), Value::Array(array)) => {
+                    if array.len() <= *index {
+                        let len = index
+                            .checked_add(1)
+                            .filter(|_| index - array.len() <= MAX_ARRAY_PADDING)
+                            .ok_or_else(|| ModelError::InvalidPath {
+                                path: path.prefix(depth + 1).to_string(),
+                                why: format!(
+                                    "index is more than {} past the end of the array",
+                                    MAX_ARRAY_PADDING
+                                ),
+                            })?;
+                        array.resize(len, Value::None);
+                    }
+                    &mut array[*index]
+                }
+                (_, value) => return Err(type_mismatch(&path, depth, value)),
+            };
+        }
+
+        *current = value;
+        Ok(self)
+    }
+
+    /// Removes the value at `path`, returning it if it existed.
+    ///
+    /// Removing an array element shifts the following elements.
+    pub fn remove_path<P: IntoValuePath>(&mut self, path: P) -> Result<Option<Value>, ModelError> {
+        let path = path.into_value_path()?;
+
+        let (parent, last) = match (path.parent(), path.last()) {
+            (Some(parent), Some(last)) => (parent, last),
+            _ => {
+                return Err(ModelError::InvalidPath {
+                    path: path.to_string(),
+                    why: "can't remove the root value".to_string(),
+                })
+            }
+        };
+
+        let parent_value = match self.get_path_mut(&parent)? {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+
+        match (last, parent_value) {
+            (PathSegment::Key(key), Value::Map(map)) => Ok(map.remove(key)),
+            (PathSegment::Index(index), Value::Array(array)) => {
+                if *index < array.len() {
+                    Ok(Some(array.remove(*index)))
+                } else {
+                    Ok(None)
+                }
+            }
+            (_, Value::None) => Ok(None),
+            (_, value) => Err(type_mismatch(&path, path.segments.len() - 1, value)),
+        }
+    }
+
+    /// Returns true if a value exists at `path`, including explicit `Value::None` values.
+    ///
+    /// Invalid paths and type mismatches are reported as missing.
+    pub fn contains_path<P: IntoValuePath>(&self, path: P) -> bool {
+        matches!(self.get_path(path), Ok(Some(_)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{value_map, value_vec};
+
+    use super::*;
+
+    fn servers() -> Value {
+        value_map! {
+            servers: value_vec![
+                value_map! { host: "a.local", port: 80 },
+                value_map! { host: "b.local", port: 81 },
+            ],
+            log: value_map! {
+                level: "info",
+                file: Value::None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_parse_path() {
+        let path = ValuePath::parse("servers[2].host").unwrap();
+        assert_eq!(
+            path.segments(),
+            &[
+                PathSegment::Key("servers".into()),
+                PathSegment::Index(2),
+                PathSegment::Key("host".into()),
+            ]
+        );
+
+        let path = ValuePath::parse(r#"log."file.path"["a\"b"][0][1]"#).unwrap();
+        assert_eq!(
+            path.segments(),
+            &[
+                PathSegment::Key("log".into()),
+                PathSegment::Key("file.path".into()),
+                PathSegment::Key("a\"b".into()),
+                PathSegment::Index(0),
+                PathSegment::Index(1),
+            ]
+        );
+
+        assert!(ValuePath::parse("").unwrap().is_root());
+        assert_eq!(ValuePath::parse("[1].a").unwrap().segments().len(), 2);
+    }
+
+    #[test]
+    fn test_parse_invalid_path() {
+        for path in [
+            "a.", ".a", "a..b", "a[", "a[x]", "a[-1]", "a]b", "\"a", "a[\"b\"", "a\"b\"",
+        ] {
+            assert!(
+                matches!(ValuePath::parse(path), Err(ModelError::InvalidPath { .. })),
+                "expected '{}' to be invalid",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_display_path() {
+        for path in [
+            "servers[2].host",
+            "[0][1]",
+            r#"log."file.path".a"#,
+            r#""a\"b""#,
+        ] {
+            assert_eq!(ValuePath::parse(path).unwrap().to_string(), path);
+        }
+    }
+
+    #[test]
+    fn test_get_path() {
+        let value = servers();
+
+        let host = value.get_path("servers[1].host").unwrap().unwrap();
+        assert_eq!(host.try_into_string().unwrap(), "b.local");
+        assert_eq!(value.get_path("servers[5].host").unwrap(), None);
+        assert_eq!(value.get_path("log.missing").unwrap(), None);
+        assert_eq!(value.get_path("log.file.path").unwrap(), None);
+        assert_eq!(value.get_path("").unwrap(), Some(&value));
+
+        let err = value.get_path("servers.host").unwrap_err();
+        assert_eq!(
+            err,
+            ModelError::PathTypeMismatch {
+                path: "servers".into(),
+                expected: "Map",
+                found: "Array",
+            }
+        );
+    }
+
+    #[test]
+    fn test_set_path() {
+        let mut value = servers();
+
+        value.set_path("servers[0].port", 8080.into()).unwrap();
+        value
+            .set_path("log.\"file.path\"", "app.log".into())
+            .unwrap();
+        value.set_path("new.list[2].name", "x".into()).unwrap();
+        value.set_path("log.file.name", "a.log".into()).unwrap();
+
+        assert_eq!(
+            value.get_path("servers[0].port").unwrap(),
+            Some(&Value::Integer(8080))
+        );
+        assert_eq!(
+            value.get("log").get("file.path").try_into_string().unwrap(),
+            "app.log"
+        );
+        assert_eq!(value.get("new").get("list").len(), 3);
+        assert!(value.get("new").get("list").at(0).is_none());
+        assert_eq!(
+            value.get_path("new.list[2].name").unwrap(),
+            Some(&"x".into())
+        );
+        assert_eq!(
+            value.get_path("log.file.name").unwrap(),
+            Some(&"a.log".into())
+        );
+
+        let err = value.set_path("log.level.value", 1.into()).unwrap_err();
+        assert_eq!(
+            err,
+            ModelError::PathTypeMismatch {
+                path: "log.level".into(),
+                expected: "Map",
+                found: "String",
+            }
+        );
+    }
+
+    #[test]
+    fn test_set_path_rejects_far_indexes() {
+        let mut value = Value::new_map();
+
+        value.set_path("list[1024]", 1.into()).unwrap();
+        assert_eq!(value.get("list").len(), 1025);
+
+        for path in [
+            "list[2050]",
+            "other[4000000000]",
+            "other[18446744073709551615]",
+        ] {
+            let err = value.set_path(path, 1.into()).unwrap_err();
+            assert!(
+                matches!(&err, ModelError::InvalidPath { path: p, .. } if p == path),
+                "{}: {}",
+                path,
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_remove_path() {
+        let mut value = servers();
+
+        let removed = value.remove_path("servers[0]").unwrap().unwrap();
+        assert_eq!(removed.get("host").try_into_string().unwrap(), "a.local");
+        assert_eq!(value.get("servers").len(), 1);
+
+        assert_eq!(value.remove_path("log.level").unwrap(), Some("info".into()));
+        assert_eq!(value.remove_path("log.level").unwrap(), None);
+        assert_eq!(value.remove_path("missing.key").unwrap(), None);
+        assert!(value.remove_path("").is_err());
+        assert!(value.remove_path("servers.host").is_err());
+    }
+
+    #[test]
+    fn test_contains_path() {
+        let value = servers();
+
+        assert!(value.contains_path("servers[1]"));
+        assert!(value.contains_path("log.file"));
+        assert!(!value.contains_path("log.file.path"));
+        assert!(!value.contains_path("servers[2]"));
+        assert!(!value.contains_path("servers.host"));
+    }
+}
@@ -0,0 +1,592 @@
+//! Describes the expected shape of a `Value` and validates values against it.
+//!
+//! Example:
+//! ```rust
+//! use bakunin_config::model::Schema;
+//! use bakunin_config::value_map;
+//!
+//! let schema = Schema::map()
+//!     .field("port", Schema::integer().range(1.0, 65535.0).required())
+//!     .field("level", Schema::string().one_of(["info", "debug"]));
+//!
+//! let errors = schema.check(&value_map! { port: 0, level: "trace" });
+//! assert_eq!(errors.len(), 2);
+//! ```
+
+use std::{collections::HashMap, fmt::Display, sync::Arc};
+
+#[cfg(feature = "schema-regex")]
+use regex::Regex;
+
+use super::{ModelError, Value, ValuePath};
+
+/// Type of value accepted by a `Schema`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    None,
+    Boolean,
+    /// Any integer value, signed or unsigned
+    Integer,
+    /// Float or integer values
+    Float,
+    String,
+    Bytes,
+    Array,
+    Map,
+}
+
+impl ValueType {
+    pub fn matches(&self, value: &Value) -> bool {
+        match (self, value) {
+            (ValueType::None, Value::None) => true,
+            (ValueType::Boolean, Value::Boolean(_)) => true,
+            (ValueType::Integer, v) | (ValueType::Float, v) if is_integer(v) => true,
+            (ValueType::Float, Value::Float(_)) => true,
+            (ValueType::String, Value::String(_)) => true,
+            (ValueType::Bytes, Value::Bytes(_)) => true,
+            (ValueType::Array, Value::Array(_)) => true,
+            (ValueType::Map, Value::Map(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Display for ValueType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+fn is_integer(value: &Value) -> bool {
+    matches!(
+        value,
+        Value::Integer(_) | Value::LongInteger(_) | Value::UInteger(_) | Value::ULongInteger(_)
+    )
+}
+
+/// Compares values, numbers are compared by value regardless of their variant.
+fn same_value(lhs: &Value, rhs: &Value) -> bool {
+    if lhs == rhs {
+        return true;
+    }
+
+    if is_integer(lhs) && is_integer(rhs) {
+        return matches!((lhs.try_into_i128(), rhs.try_into_i128()), (Ok(l), Ok(r)) if l == r)
+            || matches!((lhs.try_into_u128(), rhs.try_into_u128()), (Ok(l), Ok(r)) if l == r);
+    }
+
+    match (lhs, rhs) {
+        (Value::Float(_), v) | (v, Value::Float(_)) if is_integer(v) => {
+            matches!((lhs.try_into_f64(), rhs.try_into_f64()), (Ok(l), Ok(r)) if l == r)
+        }
+        _ => false,
+    }
+}
+
+/// A validation error, with the path of the value that failed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaError {
+    pub path: ValuePath,
+    pub message: String,
+}
+
+impl SchemaError {
+    pub fn new(path: &ValuePath, message: String) -> Self {
+        Self {
+            path: path.clone(),
+            message,
+        }
+    }
+}
+
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.path.is_root() {
+            write!(f, "(root): {}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+/// Joins errors in a single line, used in error messages.
+pub fn join_errors(errors: &[SchemaError]) -> String {
+    errors
+        .iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+type RuleCheck = Arc<dyn Fn(&Value) -> bool + Send + Sync>;
+
+/// Describes the expected type and constraints of a value.
+///
+/// Schemas are built with the constructor of the expected type, like `Schema::map()`,
+/// and chained constraints. Constraints only apply to values of a matching type,
+/// e.g. `range` is ignored for strings.
+#[derive(Clone, Default)]
+pub struct Schema {
+    types: Vec<ValueType>,
+    required: bool,
+    min: Option<f64>,
+    max: Option<f64>,
+    min_len: Option<usize>,
+    max_len: Option<usize>,
+    #[cfg(feature = "schema-regex")]
+    pattern: Option<Result<Regex, String>>,
+    allowed: Option<Vec<Value>>,
+    items: Option<Box<Schema>>,
+    fields: Vec<(String, Schema)>,
+    deny_unknown_fields: bool,
+    rules: Vec<(String, RuleCheck)>,
+}
+
+impl Schema {
+    /// Schema that accepts any value.
+    pub fn any() -> Self {
+        Self::default()
+    }
+
+    /// Schema that accepts values of any of the specified types.
+    pub fn of_types(types: &[ValueType]) -> Self {
+        Self {
+            types: types.to_vec(),
+            ..Self::default()
+        }
+    }
+
+    pub fn boolean() -> Self {
+        Self::of_types(&[ValueType::Boolean])
+    }
+
+    pub fn integer() -> Self {
+        Self::of_types(&[ValueType::Integer])
+    }
+
+    pub fn float() -> Self {
+        Self::of_types(&[ValueType::Float])
+    }
+
+    pub fn string() -> Self {
+        Self::of_types(&[ValueType::String])
+    }
+
+    pub fn bytes() -> Self {
+        Self::of_types(&[ValueType::Bytes])
+    }
+
+    pub fn array() -> Self {
+        Self::of_types(&[ValueType::Array])
+    }
+
+    pub fn map() -> Self {
+        Self::of_types(&[ValueType::Map])
+    }
+
+    /// Also accept `Value::None`.
+    pub fn nullable(mut self) -> Self {
+        if !self.types.is_empty() && !self.types.contains(&ValueType::None) {
+            self.types.push(ValueType::None);
+        }
+        self
+    }
+
+    /// The key must exist in the parent map.
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    /// Minimum value of a number, inclusive.
+    pub fn min(mut self, min: f64) -> Self {
+        self.min = Some(min);
+        self
+    }
+
+    /// Maximum value of a number, inclusive.
+    pub fn max(mut self, max: f64) -> Self {
+        self.max = Some(max);
+        self
+    }
+
+    /// Range of a number, inclusive.
+    pub fn range(self, min: f64, max: f64) -> Self {
+        self.min(min).max(max)
+    }
+
+    /// Minimum length of an array, map, bytes or string (in characters).
+    pub fn min_len(mut self, min: usize) -> Self {
+        self.min_len = Some(min);
+        self
+    }
+
+    /// Maximum length of an array, map, bytes or string (in characters).
+    pub fn max_len(mut self, max: usize) -> Self {
+        self.max_len = Some(max);
+        self
+    }
+
+    /// Regular expression that a string must match.
+    ///
+    /// An invalid expression is reported as an error when validating.
+    /// Requires the `schema-regex` feature.
+    #[cfg(feature = "schema-regex")]
+    pub fn pattern(mut self, pattern: &str) -> Self {
+        self.pattern = Some(Regex::new(pattern).map_err(|e| e.to_string()));
+        self
+    }
+
+    /// List of allowed values.
+    pub fn one_of<T, I>(mut self, values: I) -> Self
+    where
+        T: Into<Value>,
+        I: IntoIterator<Item = T>,
+    {
+        self.allowed = Some(values.into_iter().map(T::into).collect());
+        self
+    }
+
+    /// Schema of every element of an array.
+    pub fn items(mut self, schema: Schema) -> Self {
+        self.items = Some(Box::new(schema));
+        self
+    }
+
+    /// Schema of a key of a map.
+    pub fn field(mut self, key: &str, schema: Schema) -> Self {
+        self.fields.retain(|(k, _)| k != key);
+        self.fields.push((key.to_string(), schema));
+        self
+    }
+
+    /// Keys not defined with `field` are reported as errors.
+    pub fn deny_unknown_fields(mut self) -> Self {
+        self.deny_unknown_fields = true;
+        self
+    }
+
+    /// Custom rule, used to check constraints between multiple fields.
+    ///
+    /// `check` receives the value this schema is validating, `message` is reported when it
+    /// returns false.
+    pub fn rule<F>(mut self, message: &str, check: F) -> Self
+    where
+        F: Fn(&Value) -> bool + Send + Sync + 'static,
+    {
+        self.rules.push((message.to_string(), Arc::new(check)));
+        self
+    }
+
+    /// Validates `value`, returning every error found.
+    pub fn check(&self, value: &Value) -> Vec<SchemaError> {
+        let mut errors = Vec::new();
+        self.check_at(value, &mut ValuePath::root(), &mut errors);
+        errors
+    }
+
+    /// Validates `value`, returning `ModelError::SchemaValidation` with every error found.
+    pub fn validate(&self, value: &Value) -> Result<(), ModelError> {
+        let errors = self.check(value);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ModelError::SchemaValidation(errors))
+        }
+    }
+
+    fn check_at(&self, value: &Value, path: &mut ValuePath, errors: &mut Vec<SchemaError>) {
+        if !self.types.is_empty() && !self.types.iter().any(|t| t.matches(value)) {
+            let expected = self
+                .types
+                .iter()
+                .map(|t| t.to_string())
+                .collect::<Vec<_>>()
+                .join(" or ");
+            errors.push(SchemaError::new(
+                path,
+                format!("expected {}, found {}", expected, value.type_name()),
+            ));
+            return;
+        }
+
+        if let Some(allowed) = &self.allowed {
+            if !allowed.iter().any(|a| same_value(a, value)) {
+                let allowed = allowed
+                    .iter()
+                    .map(|a| a.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                errors.push(SchemaError::new(
+                    path,
+                    format!("{} is not one of [{}]", value, allowed),
+                ));
+            }
+        }
+
+        self.check_number(value, path, errors);
+        self.check_len(value, path, errors);
+        #[cfg(feature = "schema-regex")]
+        self.check_pattern(value, path, errors);
+
+        match value {
+            Value::Array(array) => {
+                if let Some(items) = &self.items {
+                    for (i, item) in array.iter().enumerate() {
+                        path.push_index(i);
+                        items.check_at(item, path, errors);
+                        path.pop();
+                    }
+                }
+            }
+            Value::Map(map) => self.check_fields(map, path, errors),
+            _ => {}
+        }
+
+        for (message, check) in &self.rules {
+            if !check(value) {
+                errors.push(SchemaError::new(path, message.clone()));
+            }
+        }
+    }
+
+    fn check_number(&self, value: &Value, path: &ValuePath, errors: &mut Vec<SchemaError>) {
+        if !is_integer(value) && !matches!(value, Value::Float(_)) {
+            return;
+        }
+
+        let number = match value.try_into_f64() {
+            Ok(number) => number,
+            Err(_) => return,
+        };
+
+        if let Some(min) = self.min {
+            if number < min {
+                errors.push(SchemaError::new(
+                    path,
+                    format!("{} is less than the minimum {}", value, min),
+                ));
+            }
+        }
+
+        if let Some(max) = self.max {
+            if number > max {
+                errors.push(SchemaError::new(
+                    path,
+                    format!("{} is greater than the maximum {}", value, max),
+                ));
+            }
+        }
+    }
+
+    fn check_len(&self, value: &Value, path: &ValuePath, errors: &mut Vec<SchemaError>) {
+        let len = match value {
+            Value::String(s) => s.chars().count(),
+            Value::Bytes(_) | Value::Array(_) | Value::Map(_) => value.len(),
+            _ => return,
+        };
+
+        if let Some(min) = self.min_len {
+            if len < min {
+                errors.push(SchemaError::new(
+                    path,
+                    format!("length {} is less than the minimum {}", len, min),
+                ));
+            }
+        }
+
+        if let Some(max) = self.max_len {
+            if len > max {
+                errors.push(SchemaError::new(
+                    path,
+                    format!("length {} is greater than the maximum {}", len, max),
+                ));
+            }
+        }
+    }
+
+    #[cfg(feature = "schema-regex")]
+    fn check_pattern(&self, value: &Value, path: &ValuePath, errors: &mut Vec<SchemaError>) {
+        let (pattern, value) = match (&self.pattern, value) {
+            (Some(pattern), Value::String(value)) => (pattern, value),
+            _ => return,
+        };
+
+        match pattern {
+            Ok(regex) if !regex.is_match(value) => errors.push(SchemaError::new(
+                path,
+                format!("'{}' doesn't match pattern '{}'", value, regex.as_str()),
+            )),
+            Ok(_) => {}
+            Err(e) => errors.push(SchemaError::new(path, format!("invalid pattern: {}", e))),
+        }
+    }
+
+    fn check_fields(
+        &self,
+        map: &HashMap<String, Value>,
+        path: &mut ValuePath,
+        errors: &mut Vec<SchemaError>,
+    ) {
+        for (key, schema) in &self.fields {
+            path.push_key(key.as_str());
+            match map.get(key) {
+                Some(value) => schema.check_at(value, path, errors),
+                None if schema.required => errors.push(SchemaError::new(
+                    path,
+                    "required key is missing".to_string(),
+                )),
+                None => {}
+            }
+            path.pop();
+        }
+
+        if self.deny_unknown_fields {
+            let mut unknown: Vec<&String> = map
+                .keys()
+                .filter(|k| !self.fields.iter().any(|(f, _)| f == *k))
+                .collect();
+            unknown.sort();
+
+            for key in unknown {
+                path.push_key(key.as_str());
+                errors.push(SchemaError::new(path, "unknown key".to_string()));
+                path.pop();
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for Schema {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rules: Vec<&String> = self.rules.iter().map(|(m, _)| m).collect();
+
+        let mut debug = f.debug_struct("Schema");
+        debug
+            .field("types", &self.types)
+            .field("required", &self.required)
+            .field("min", &self.min)
+            .field("max", &self.max)
+            .field("min_len", &self.min_len)
+            .field("max_len", &self.max_len);
+        #[cfg(feature = "schema-regex")]
+        debug.field("pattern", &self.pattern);
+        debug
+            .field("allowed", &self.allowed)
+            .field("items", &self.items)
+            .field("fields", &self.fields)
+            .field("deny_unknown_fields", &self.deny_unknown_fields)
+            .field("rules", &rules)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{value_map, value_vec};
+
+    use super::*;
+
+    fn schema() -> Schema {
+        Schema::map()
+            .field("port", Schema::integer().range(1.0, 65535.0).required())
+            .field(
+                "log",
+                Schema::map()
+                    .field("level", Schema::string().one_of(["info", "debug"]))
+                    .field("file", Schema::string().nullable()),
+            )
+            .field(
+                "hosts",
+                Schema::array()
+                    .items(Schema::string().max_len(8))
+                    .min_len(1)
+                    .max_len(2),
+            )
+            .field("ratio", Schema::float())
+            .field("tls", Schema::boolean())
+            .rule("tls requires a cert", |v| {
+                !v.get("tls").into_bool_or(false) || v.contains_path("cert")
+            })
+    }
+
+    fn messages(errors: Vec<SchemaError>) -> Vec<String> {
+        let mut messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        messages.sort();
+        messages
+    }
+
+    #[test]
+    fn test_accepts_valid_value() {
+        let value = value_map! {
+            port: 8080u16,
+            log: value_map! { level: "debug", file: Value::None },
+            hosts: value_vec!["a.local"],
+            ratio: 1,
+            extra: true,
+        };
+
+        assert_eq!(schema().check(&value), vec![]);
+        assert!(schema().validate(&value).is_ok());
+    }
+
+    #[test]
+    fn test_reports_all_errors() {
+        let value = value_map! {
+            log: value_map! { level: "trace", file: 10 },
+            hosts: value_vec!["a.local", "b.remote.host", "c"],
+            ratio: "1.0",
+            tls: true,
+        };
+
+        assert_eq!(
+            messages(schema().check(&value)),
+            vec![
+                "(root): tls requires a cert",
+                "hosts: length 3 is greater than the maximum 2",
+                "hosts[1]: length 13 is greater than the maximum 8",
+                "log.file: expected String or None, found Integer",
+                "log.level: trace is not one of [info, debug]",
+                "port: required key is missing",
+                "ratio: expected Float, found String",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_checks_ranges_and_unknown_fields() {
+        let schema = Schema::map()
+            .field("port", Schema::integer().range(1.0, 10.0))
+            .field("level", Schema::any().one_of([1]))
+            .deny_unknown_fields();
+
+        let value = value_map! { port: 0, level: 1u8, other: 1 };
+
+        assert_eq!(
+            messages(schema.check(&value)),
+            vec!["other: unknown key", "port: 0 is less than the minimum 1"]
+        );
+    }
+
+    #[cfg(feature = "schema-regex")]
+    #[test]
+    fn test_checks_patterns() {
+        let schema = Schema::map()
+            .field(
+                "hosts",
+                Schema::array().items(Schema::string().pattern("^[a-z.]+$")),
+            )
+            .field("bad", Schema::string().pattern("("));
+
+        let value = value_map! { hosts: value_vec!["a.local", "B"], bad: "x" };
+
+        let messages = messages(schema.check(&value));
+
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].starts_with("bad: invalid pattern: "));
+        assert_eq!(
+            messages[1],
+            "hosts[1]: 'B' doesn't match pattern '^[a-z.]+$'"
+        );
+    }
+}
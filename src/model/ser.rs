@@ -16,6 +16,13 @@ impl serde::ser::Serialize for Value {
             Value::ULongInteger(value) => s.serialize_u128(*value),
             Value::Float(value) => s.serialize_f64(*value),
             Value::String(value) => s.serialize_str(value),
+            Value::Bytes(value) => {
+                if s.is_human_readable() {
+                    s.serialize_str(&super::bytes::encode_base64(value))
+                } else {
+                    s.serialize_bytes(value)
+                }
+            }
             Value::Array(value) => {
                 let mut seq = s.serialize_seq(Some(value.len()))?;
                 for v in value {
@@ -117,11 +124,7 @@ impl serde::Serializer for ValueSerializer {
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
-        let mut value = Value::new_array();
-        v.iter().for_each(|e| {
-            value.push(Value::UInteger(e.to_owned().into())).unwrap();
-        });
-        Ok(value)
+        Ok(Value::Bytes(v.to_vec()))
     }
 
     fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
@@ -500,6 +503,16 @@ mod tests {
         assert_eq!(serialized, "\"42\"");
     }
 
+    #[test]
+    fn test_model_value_serialize_bytes() {
+        let value = Value::Bytes(vec![0, 1, 254, 255]);
+        let serialized = serde_json::to_string(&value).unwrap();
+        assert_eq!(serialized, "\"AAH+/w==\"");
+
+        let value = Value::serialize(serde_bytes::Bytes::new(&[1, 2])).unwrap();
+        assert_eq!(value, Value::Bytes(vec![1, 2]));
+    }
+
     #[test]
     fn test_model_value_serialize_array() {
         let value = Value::Array(vec![Value::Integer(42), Value::Integer(43)]);
@@ -13,6 +13,7 @@ pub enum Value {
     ULongInteger(u128),
     Float(f64),
     String(String),
+    Bytes(Vec<u8>),
     Array(Vec<Value>),
     Map(HashMap<String, Value>),
 }
@@ -28,6 +29,7 @@ impl Display for Value {
             Value::ULongInteger(value) => write!(f, "{}", value),
             Value::Float(value) => write!(f, "{}", value),
             Value::String(value) => write!(f, "{}", value),
+            Value::Bytes(value) => write!(f, "{}", super::bytes::encode_base64(value)),
             Value::Array(value) => {
                 write!(f, "[")?;
                 for (i, value) in value.iter().enumerate() {
@@ -98,6 +100,7 @@ impl Value {
             Value::Array(array) => array.len(),
             Value::Map(map) => map.len(),
             Value::String(string) => string.len(),
+            Value::Bytes(bytes) => bytes.len(),
             _ => 0,
         }
     }
@@ -114,6 +117,23 @@ impl Value {
         matches!(self, Value::None)
     }
 
+    /// Name of the value variant, used in error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::None => "None",
+            Value::Boolean(_) => "Boolean",
+            Value::Integer(_) => "Integer",
+            Value::LongInteger(_) => "LongInteger",
+            Value::UInteger(_) => "UInteger",
+            Value::ULongInteger(_) => "ULongInteger",
+            Value::Float(_) => "Float",
+            Value::String(_) => "String",
+            Value::Bytes(_) => "Bytes",
+            Value::Array(_) => "Array",
+            Value::Map(_) => "Map",
+        }
+    }
+
     pub fn iter<'a>(&'a self) -> ValueIter<'a> {
         ValueIter::new(self)
     }
@@ -149,6 +169,9 @@ mod tests {
         let value = Value::String("test".to_string());
         assert_eq!(format!("{}", value), "test");
 
+        let value = Value::Bytes(vec![1, 2, 3]);
+        assert_eq!(format!("{}", value), "AQID");
+
         let value = Value::Array(vec![Value::Integer(1), Value::Integer(2)]);
         assert_eq!(format!("{}", value), "[1, 2]");
 
@@ -97,6 +97,18 @@ impl<'de> Visitor<'de> for ValueVisitor {
         Ok(Value::String(value))
     }
 
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+        where
+            E: de::Error, {
+        Ok(Value::Bytes(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+        where
+            E: de::Error, {
+        Ok(Value::Bytes(v))
+    }
+
     fn visit_none<E>(self) -> Result<Self::Value, E>
     where
         E: de::Error {